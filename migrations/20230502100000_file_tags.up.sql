@@ -0,0 +1,8 @@
+create table if not exists files_per_tag
+(
+    files_pk uuid not null
+    constraint files_per_tag_file_fk
+    references file on delete cascade not null,
+    tag varchar(50) not null,
+    primary key (files_pk, tag)
+    );
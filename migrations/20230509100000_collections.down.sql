@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS files_per_collection;
+DROP TABLE IF EXISTS collection;
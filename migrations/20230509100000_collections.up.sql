@@ -0,0 +1,20 @@
+create table if not exists collection
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    name varchar(255) not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    owner_user_pk uuid not null
+    constraint collection_user_account_fk
+    references user_account not null
+    );
+
+create table if not exists files_per_collection
+(
+    collection_pk uuid not null
+    constraint files_per_collection_collection_fk
+    references collection on delete cascade not null,
+    files_pk uuid not null
+    constraint files_per_collection_file_fk
+    references file on delete cascade not null,
+    primary key (collection_pk, files_pk)
+    );
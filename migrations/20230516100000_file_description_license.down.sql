@@ -0,0 +1,3 @@
+alter table file
+    drop column if exists description,
+    drop column if exists license;
@@ -0,0 +1,3 @@
+alter table file
+    add column if not exists description text,
+    add column if not exists license varchar(100);
@@ -0,0 +1,2 @@
+alter table file
+    drop column if exists version;
@@ -0,0 +1,2 @@
+alter table file
+    add column if not exists version integer default 1 not null;
@@ -0,0 +1,2 @@
+alter table print
+    drop column if exists user_account_fk;
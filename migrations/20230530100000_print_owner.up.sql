@@ -0,0 +1,4 @@
+alter table print
+    add column if not exists user_account_fk uuid
+    constraint print_user_account_fk
+    references user_account;
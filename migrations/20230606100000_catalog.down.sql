@@ -0,0 +1,19 @@
+alter table material
+    drop constraint if exists material_brand_description_key;
+
+alter table printer
+    drop constraint if exists printer_brand_model_key;
+
+alter table material_brand
+    drop constraint if exists material_brand_full_name_key;
+
+alter table printer_brand
+    drop constraint if exists printer_brand_full_name_key;
+
+alter table material
+    alter column mat_type type varchar(24) using mat_type::text;
+
+DROP TYPE IF EXISTS material_type;
+
+alter table user_account
+    drop column if exists is_admin;
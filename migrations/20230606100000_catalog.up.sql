@@ -0,0 +1,19 @@
+alter table user_account
+    add column if not exists is_admin boolean default false not null;
+
+CREATE TYPE material_type AS ENUM ('PLA', 'PETG', 'ABS', 'Composite', 'Resin');
+
+alter table material
+    alter column mat_type type material_type using mat_type::material_type;
+
+alter table printer_brand
+    add constraint printer_brand_full_name_key unique (full_name);
+
+alter table material_brand
+    add constraint material_brand_full_name_key unique (full_name);
+
+alter table printer
+    add constraint printer_brand_model_key unique (printer_brand_fk, model);
+
+alter table material
+    add constraint material_brand_description_key unique (material_brand_fk, description);
@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS print_photo;
+
+alter table print
+    drop constraint if exists print_failure_category_check,
+    drop column if exists notes,
+    drop column if exists failure_category;
+
+DROP TYPE IF EXISTS failure_category;
@@ -0,0 +1,17 @@
+CREATE TYPE failure_category AS ENUM ('warping', 'stringing', 'layer_shift', 'adhesion', 'spaghetti', 'other');
+
+alter table print
+    add column if not exists failure_category failure_category,
+    add column if not exists notes text,
+    add constraint print_failure_category_check check (not successful or failure_category is null);
+
+create table if not exists print_photo
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    print_fk uuid not null
+    constraint print_photo_print_fk
+    references print on delete cascade not null,
+    content_type varchar(100) not null,
+    sizebytes bigint not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW()
+    );
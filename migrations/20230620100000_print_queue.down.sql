@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS print_job;
+DROP TABLE IF EXISTS user_printer;
+DROP TYPE IF EXISTS job_status;
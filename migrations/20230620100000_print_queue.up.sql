@@ -0,0 +1,40 @@
+CREATE TYPE job_status AS ENUM ('queued', 'printing', 'done', 'failed', 'cancelled');
+
+create table if not exists user_printer
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    name varchar(100) not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    user_account_fk uuid not null
+    constraint user_printer_user_account_fk
+    references user_account not null,
+    printer_fk uuid not null
+    constraint user_printer_printer_fk
+    references printer not null,
+    unique (user_account_fk, name)
+    );
+
+create table if not exists print_job
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    user_printer_fk uuid not null
+    constraint print_job_user_printer_fk
+    references user_printer on delete cascade not null,
+    gcode_fk uuid not null
+    constraint print_job_gcode_fk
+    references gcode not null,
+    material_fk uuid
+    constraint print_job_material_fk
+    references material,
+    priority integer default 0 not null,
+    position integer default 0 not null,
+    status job_status default 'queued' not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    started timestamp WITH TIME ZONE,
+    finished timestamp WITH TIME ZONE,
+    print_fk uuid
+    constraint print_job_print_fk
+    references print on delete set null
+    );
+
+create index if not exists print_job_queue_idx on print_job (user_printer_fk, status, priority, position);
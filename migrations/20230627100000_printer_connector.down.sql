@@ -0,0 +1,9 @@
+alter table print_job
+    drop column if exists progress;
+
+alter table user_printer
+    drop column if exists connector_api_key,
+    drop column if exists connector_url,
+    drop column if exists connector_kind;
+
+DROP TYPE IF EXISTS connector_kind;
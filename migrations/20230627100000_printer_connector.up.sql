@@ -0,0 +1,9 @@
+CREATE TYPE connector_kind AS ENUM ('octoprint', 'moonraker');
+
+alter table user_printer
+    add column if not exists connector_kind connector_kind,
+    add column if not exists connector_url varchar(255),
+    add column if not exists connector_api_key varchar(255);
+
+alter table print_job
+    add column if not exists progress real;
@@ -0,0 +1,13 @@
+DROP FUNCTION IF EXISTS filament_grams;
+
+alter table print
+    drop column if exists created;
+
+alter table gcode
+    drop column if exists filament_length_mm;
+
+alter table material
+    drop constraint if exists material_physical_properties_check,
+    drop column if exists price_per_kg,
+    drop column if exists diameter_mm,
+    drop column if exists density_g_cm3;
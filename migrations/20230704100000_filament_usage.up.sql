@@ -0,0 +1,25 @@
+alter table material
+    add column if not exists density_g_cm3 double precision,
+    add column if not exists diameter_mm double precision default 1.75 not null,
+    add column if not exists price_per_kg double precision,
+    add constraint material_physical_properties_check
+        check (density_g_cm3 > 0 and diameter_mm > 0 and price_per_kg >= 0);
+
+alter table gcode
+    add column if not exists filament_length_mm double precision;
+
+alter table print
+    add column if not exists created timestamp WITH TIME ZONE DEFAULT NOW();
+
+UPDATE print SET created = print_job.finished
+FROM print_job
+WHERE print_job.print_fk = print.id AND print_job.finished IS NOT NULL;
+
+-- Grams of filament of the given diameter and density needed for length_mm.
+CREATE OR REPLACE FUNCTION filament_grams(
+    length_mm double precision,
+    diameter_mm double precision,
+    density_g_cm3 double precision
+) RETURNS double precision AS $$
+    SELECT length_mm * pi() * (diameter_mm / 2) ^ 2 / 1000 * density_g_cm3
+$$ LANGUAGE sql IMMUTABLE;
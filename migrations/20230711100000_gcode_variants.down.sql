@@ -0,0 +1,8 @@
+drop index if exists gcode_model_file_idx;
+
+alter table gcode
+    drop constraint if exists gcode_model_not_self_check,
+    drop column if exists label,
+    drop column if exists material_fk,
+    drop column if exists printer_fk,
+    drop column if exists model_file_fk;
@@ -0,0 +1,11 @@
+alter table gcode
+    add column if not exists model_file_fk uuid
+        constraint gcode_model_file_fk references file on delete set null,
+    add column if not exists printer_fk uuid
+        constraint gcode_printer_fk references printer,
+    add column if not exists material_fk uuid
+        constraint gcode_material_fk references material,
+    add column if not exists label varchar(255),
+    add constraint gcode_model_not_self_check check (model_file_fk <> file_pk);
+
+create index if not exists gcode_model_file_idx on gcode (model_file_fk);
@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS mail_verification_token;
+
+drop index if exists user_account_mails_one_primary_idx;
+
+alter table user_account_mails
+    drop column if exists created,
+    drop column if exists is_primary,
+    drop column if exists is_verified;
@@ -0,0 +1,21 @@
+alter table user_account_mails
+    add column if not exists is_verified boolean default false not null,
+    add column if not exists is_primary boolean default false not null,
+    add column if not exists created timestamp WITH TIME ZONE DEFAULT NOW();
+
+-- Mails registered before verification existed keep resolving; every account gets a primary.
+UPDATE user_account_mails SET is_verified = true;
+UPDATE user_account_mails SET is_primary = true
+WHERE mail IN (SELECT DISTINCT ON (user_account_pk) mail FROM user_account_mails ORDER BY user_account_pk, mail);
+
+create unique index if not exists user_account_mails_one_primary_idx
+    on user_account_mails (user_account_pk) where is_primary;
+
+create table if not exists mail_verification_token
+(
+    token_hash varchar(64) PRIMARY KEY NOT NULL,
+    mail varchar(100) not null
+    constraint mail_verification_token_mail_fk
+    references user_account_mails on delete cascade on update cascade,
+    expires timestamp WITH TIME ZONE not null
+    );
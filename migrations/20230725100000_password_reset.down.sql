@@ -0,0 +1,6 @@
+DROP TABLE IF EXISTS password_reset_request;
+DROP TABLE IF EXISTS password_reset_token;
+DROP TABLE IF EXISTS user_session;
+
+alter table user_account
+    drop column if exists password_hash;
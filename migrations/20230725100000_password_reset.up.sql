@@ -0,0 +1,32 @@
+alter table user_account
+    add column if not exists password_hash varchar(255);
+
+create table if not exists user_session
+(
+    token_hash varchar(64) PRIMARY KEY NOT NULL,
+    user_account_fk uuid not null
+    constraint user_session_user_account_fk
+    references user_account on delete cascade,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    expires timestamp WITH TIME ZONE not null
+    );
+
+create index if not exists user_session_user_account_idx on user_session (user_account_fk);
+
+create table if not exists password_reset_token
+(
+    token_hash varchar(64) PRIMARY KEY NOT NULL,
+    user_account_fk uuid not null
+    constraint password_reset_token_user_account_fk
+    references user_account on delete cascade,
+    expires timestamp WITH TIME ZONE not null
+    );
+
+-- Every reset request, also for unknown mails, so rate limiting does not reveal which mails exist.
+create table if not exists password_reset_request
+(
+    mail varchar(100) not null,
+    requested timestamp WITH TIME ZONE DEFAULT NOW() not null
+    );
+
+create index if not exists password_reset_request_mail_idx on password_reset_request (mail, requested);
@@ -0,0 +1,9 @@
+alter table user_account
+    drop constraint if exists user_account_website_check,
+    drop constraint if exists user_account_bio_length_check,
+    drop column if exists avatar_content_type,
+    drop column if exists avatar_id,
+    drop column if exists website,
+    drop column if exists location,
+    drop column if exists bio,
+    drop column if exists display_name;
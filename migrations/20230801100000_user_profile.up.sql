@@ -0,0 +1,9 @@
+alter table user_account
+    add column if not exists display_name varchar(100),
+    add column if not exists bio text,
+    add column if not exists location varchar(100),
+    add column if not exists website varchar(255),
+    add column if not exists avatar_id uuid,
+    add column if not exists avatar_content_type varchar(50),
+    add constraint user_account_bio_length_check check (char_length(bio) <= 1000),
+    add constraint user_account_website_check check (website ~ '^https?://');
@@ -0,0 +1,24 @@
+alter table print
+    drop constraint if exists print_user_account_fk,
+    add constraint print_user_account_fk
+        foreign key (user_account_fk) references user_account;
+
+alter table user_printer
+    drop constraint if exists user_printer_user_account_fk,
+    add constraint user_printer_user_account_fk
+        foreign key (user_account_fk) references user_account;
+
+alter table collection
+    drop constraint if exists collection_user_account_fk,
+    add constraint collection_user_account_fk
+        foreign key (owner_user_pk) references user_account;
+
+alter table files_per_user
+    drop constraint if exists files_per_user_user_account_fk,
+    add constraint files_per_user_user_account_fk
+        foreign key (user_account_pk) references user_account;
+
+alter table user_account_mails
+    drop constraint if exists user_account_mails_user_account_fk,
+    add constraint user_account_mails_user_account_fk
+        foreign key (user_account_pk) references user_account;
@@ -0,0 +1,26 @@
+-- Deleting an account takes its mails, grants, collections and printers with it.
+-- Prints stay in the statistics but lose their author.
+alter table user_account_mails
+    drop constraint if exists user_account_mails_user_account_fk,
+    add constraint user_account_mails_user_account_fk
+        foreign key (user_account_pk) references user_account on delete cascade;
+
+alter table files_per_user
+    drop constraint if exists files_per_user_user_account_fk,
+    add constraint files_per_user_user_account_fk
+        foreign key (user_account_pk) references user_account on delete cascade;
+
+alter table collection
+    drop constraint if exists collection_user_account_fk,
+    add constraint collection_user_account_fk
+        foreign key (owner_user_pk) references user_account on delete cascade;
+
+alter table user_printer
+    drop constraint if exists user_printer_user_account_fk,
+    add constraint user_printer_user_account_fk
+        foreign key (user_account_fk) references user_account on delete cascade;
+
+alter table print
+    drop constraint if exists print_user_account_fk,
+    add constraint print_user_account_fk
+        foreign key (user_account_fk) references user_account on delete set null;
@@ -0,0 +1,2 @@
+drop table if exists api_key;
+drop type if exists api_key_scope;
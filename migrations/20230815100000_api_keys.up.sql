@@ -0,0 +1,17 @@
+CREATE TYPE api_key_scope AS ENUM ('read', 'upload', 'manage');
+
+create table if not exists api_key
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    user_account_fk uuid not null
+    constraint api_key_user_account_fk
+    references user_account on delete cascade,
+    name varchar(100) not null,
+    token_hash varchar(64) not null unique,
+    scopes api_key_scope[] not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    expires timestamp WITH TIME ZONE,
+    last_used timestamp WITH TIME ZONE,
+    unique (user_account_fk, name),
+    constraint api_key_scopes_check check (cardinality(scopes) > 0)
+    );
@@ -0,0 +1,17 @@
+drop index if exists abuse_report_open_idx;
+drop table if exists abuse_report;
+
+alter table file
+    drop column if exists is_hidden;
+
+alter table user_account
+    add column if not exists is_admin boolean default false not null;
+
+UPDATE user_account SET is_admin = true WHERE role = 'admin';
+
+alter table user_account
+    drop column if exists suspension_reason,
+    drop column if exists suspended,
+    drop column if exists role;
+
+drop type if exists user_role;
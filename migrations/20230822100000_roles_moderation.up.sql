@@ -0,0 +1,35 @@
+CREATE TYPE user_role AS ENUM ('user', 'moderator', 'admin');
+
+alter table user_account
+    add column if not exists role user_role default 'user' not null,
+    add column if not exists suspended timestamp WITH TIME ZONE,
+    add column if not exists suspension_reason text;
+
+UPDATE user_account SET role = 'admin' WHERE is_admin;
+
+alter table user_account
+    drop column if exists is_admin;
+
+-- Hidden files stay visible to the users they are shared with, but not to the public.
+alter table file
+    add column if not exists is_hidden boolean default false not null;
+
+create table if not exists abuse_report
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    file_fk uuid not null
+    constraint abuse_report_file_fk
+    references file on delete cascade,
+    reporter_fk uuid
+    constraint abuse_report_reporter_fk
+    references user_account on delete set null,
+    reason text not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    resolved timestamp WITH TIME ZONE,
+    resolved_by_fk uuid
+    constraint abuse_report_resolved_by_fk
+    references user_account on delete set null,
+    constraint abuse_report_reason_length_check check (char_length(reason) between 1 and 2000)
+    );
+
+create index if not exists abuse_report_open_idx on abuse_report (created) where resolved is null;
@@ -0,0 +1,11 @@
+drop view if exists effective_file_role;
+drop function if exists file_permission_rank(varchar);
+
+drop index if exists file_organization_idx;
+alter table file
+    drop column if exists organization_fk;
+
+drop index if exists organization_member_user_account_idx;
+drop table if exists organization_member;
+drop table if exists organization;
+drop type if exists organization_role;
@@ -0,0 +1,51 @@
+CREATE TYPE organization_role AS ENUM ('admin', 'member', 'guest');
+
+create table if not exists organization
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    name varchar(100) not null unique,
+    created timestamp WITH TIME ZONE DEFAULT NOW()
+    );
+
+create table if not exists organization_member
+(
+    organization_fk uuid not null
+    constraint organization_member_organization_fk
+    references organization on delete cascade,
+    user_account_fk uuid not null
+    constraint organization_member_user_account_fk
+    references user_account on delete cascade,
+    role organization_role default 'member' not null,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    primary key (organization_fk, user_account_fk)
+    );
+
+create index if not exists organization_member_user_account_idx on organization_member (user_account_fk);
+
+-- An organization with files cannot be deleted until they are moved out.
+alter table file
+    add column if not exists organization_fk uuid
+    constraint file_organization_fk
+    references organization;
+
+create index if not exists file_organization_idx on file (organization_fk);
+
+-- Lower is stronger.
+CREATE OR REPLACE FUNCTION file_permission_rank(permission varchar) RETURNS integer AS $$
+    SELECT CASE permission WHEN 'owner' THEN 0 WHEN 'download' THEN 1 ELSE 2 END
+$$ LANGUAGE sql IMMUTABLE;
+
+-- The strongest role every user has on every file, from direct grants and from the
+-- organization owning the file. Admins of the organization act as owners, members may
+-- download and guests may read.
+CREATE OR REPLACE VIEW effective_file_role AS
+SELECT DISTINCT ON (user_account_pk, files_pk) user_account_pk, files_pk, roles_pk
+FROM (
+    SELECT user_account_pk, files_pk, roles_pk FROM files_per_user
+    UNION ALL
+    SELECT om.user_account_fk, f.id,
+        CASE om.role WHEN 'admin' THEN 'owner' WHEN 'member' THEN 'download' ELSE 'read' END
+    FROM file f
+        JOIN organization_member om ON om.organization_fk = f.organization_fk
+) grants
+ORDER BY user_account_pk, files_pk, file_permission_rank(roles_pk);
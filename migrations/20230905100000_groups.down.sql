@@ -0,0 +1,17 @@
+CREATE OR REPLACE VIEW effective_file_role AS
+SELECT DISTINCT ON (user_account_pk, files_pk) user_account_pk, files_pk, roles_pk
+FROM (
+    SELECT user_account_pk, files_pk, roles_pk FROM files_per_user
+    UNION ALL
+    SELECT om.user_account_fk, f.id,
+        CASE om.role WHEN 'admin' THEN 'owner' WHEN 'member' THEN 'download' ELSE 'read' END
+    FROM file f
+        JOIN organization_member om ON om.organization_fk = f.organization_fk
+) grants
+ORDER BY user_account_pk, files_pk, file_permission_rank(roles_pk);
+
+drop index if exists files_per_group_file_idx;
+drop table if exists files_per_group;
+drop index if exists user_group_member_user_account_idx;
+drop table if exists user_group_member;
+drop table if exists user_group;
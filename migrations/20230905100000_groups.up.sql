@@ -0,0 +1,57 @@
+create table if not exists user_group
+(
+    id uuid DEFAULT (uuid_generate_v4()) PRIMARY KEY NOT NULL,
+    name varchar(100) not null,
+    owner_user_fk uuid not null
+    constraint user_group_owner_fk
+    references user_account on delete cascade,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    unique (owner_user_fk, name)
+    );
+
+create table if not exists user_group_member
+(
+    group_fk uuid not null
+    constraint user_group_member_group_fk
+    references user_group on delete cascade,
+    user_account_fk uuid not null
+    constraint user_group_member_user_account_fk
+    references user_account on delete cascade,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    primary key (group_fk, user_account_fk)
+    );
+
+create index if not exists user_group_member_user_account_idx on user_group_member (user_account_fk);
+
+create table if not exists files_per_group
+(
+    group_fk uuid not null
+    constraint files_per_group_group_fk
+    references user_group on delete cascade,
+    roles_pk varchar(10) not null
+    constraint files_per_group_roles_fk
+    references file_permissions,
+    files_pk uuid not null
+    constraint files_per_group_file_fk
+    references file on delete cascade,
+    primary key (group_fk, files_pk)
+    );
+
+create index if not exists files_per_group_file_idx on files_per_group (files_pk);
+
+-- Adds grants through groups to the strongest role per user and file.
+CREATE OR REPLACE VIEW effective_file_role AS
+SELECT DISTINCT ON (user_account_pk, files_pk) user_account_pk, files_pk, roles_pk
+FROM (
+    SELECT user_account_pk, files_pk, roles_pk FROM files_per_user
+    UNION ALL
+    SELECT om.user_account_fk, f.id,
+        CASE om.role WHEN 'admin' THEN 'owner' WHEN 'member' THEN 'download' ELSE 'read' END
+    FROM file f
+        JOIN organization_member om ON om.organization_fk = f.organization_fk
+    UNION ALL
+    SELECT gm.user_account_fk, fpg.files_pk, fpg.roles_pk
+    FROM files_per_group fpg
+        JOIN user_group_member gm ON gm.group_fk = fpg.group_fk
+) grants
+ORDER BY user_account_pk, files_pk, file_permission_rank(roles_pk);
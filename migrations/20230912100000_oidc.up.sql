@@ -0,0 +1,23 @@
+-- Accounts at an OpenID Connect provider, identified by issuer and subject as the spec demands.
+create table if not exists user_identity
+(
+    issuer varchar(255) not null,
+    subject varchar(255) not null,
+    user_account_fk uuid not null
+    constraint user_identity_user_account_fk
+    references user_account on delete cascade,
+    created timestamp WITH TIME ZONE DEFAULT NOW(),
+    last_login timestamp WITH TIME ZONE,
+    primary key (issuer, subject)
+    );
+
+create index if not exists user_identity_user_account_idx on user_identity (user_account_fk);
+
+-- Started logins, until the provider redirects back. The state is only stored hashed.
+create table if not exists oidc_login_state
+(
+    state_hash varchar(64) PRIMARY KEY NOT NULL,
+    nonce varchar(64) not null,
+    code_verifier varchar(128) not null,
+    expires timestamp WITH TIME ZONE not null
+    );
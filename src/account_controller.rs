@@ -0,0 +1,171 @@
+use crate::{
+    auth::{bearer_token, AuthUser},
+    auth_controller::session_required,
+    bundle::{zip_response, BundleEntry},
+    password,
+    query_service::{
+        auth_queries::{is_recent_session, select_password_hash},
+        oidc_queries::select_identities_of_user,
+        queue_queries::select_user_printers,
+        user_queries::{select_avatar, select_mails, select_profile},
+    },
+    schema::DeleteAccount,
+    storage::image_extension,
+    token, AppState,
+};
+use actix_web::{delete, get, web, HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+use serde_json::json;
+use crate::query_service::account_queries::*;
+
+/// Accounts without a password confirm their deletion by a login at most this many minutes ago.
+const RECENT_LOGIN_MINUTES: i32 = 5;
+
+fn account_error(e: impl std::fmt::Debug) -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn json_entry(name: &str, value: &impl Serialize) -> serde_json::Result<BundleEntry> {
+    serde_json::to_vec_pretty(value).map(|content| BundleEntry::inline(name, content))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "ZIP with profile.json, mails.json, files.json, prints.json, print_photos.json, \
+collections.json, printers.json, print_jobs.json and identities.json, plus the owned files below files/, the print \
+photos below print_photos/ and the avatar. Ratings are only kept as an average per file and there are no comments, \
+so neither is part of the export.", content_type = "application/zip"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/users/me/export")]
+pub async fn export_account(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let profile = match select_profile(user.id, &data).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(json!({"status": "fail","message": "User not found"}));
+        }
+        Err(e) => return account_error(e),
+    };
+    let exported = tokio::try_join!(
+        select_mails(user.id, &data),
+        select_owned_files(user.id, &data),
+        select_prints_of_user(user.id, &data),
+        select_print_photos_of_user(user.id, &data),
+        select_collections_of_user(user.id, &data),
+        select_user_printers(user.id, &data),
+        select_jobs_of_user(user.id, &data),
+        select_avatar(user.id, &data),
+        select_identities_of_user(user.id, &data),
+    );
+    let (mails, files, prints, photos, collections, printers, jobs, avatar, identities) = match exported {
+        Ok(exported) => exported,
+        Err(e) => return account_error(e),
+    };
+
+    let documents = [
+        json_entry("profile.json", &profile),
+        json_entry("mails.json", &mails),
+        json_entry("files.json", &files),
+        json_entry("prints.json", &prints),
+        json_entry("print_photos.json", &photos),
+        json_entry("collections.json", &collections),
+        json_entry("printers.json", &printers),
+        json_entry("print_jobs.json", &jobs),
+        json_entry("identities.json", &identities),
+    ];
+    let mut entries = Vec::with_capacity(documents.len() + files.len() + photos.len() + 1);
+    for document in documents {
+        match document {
+            Ok(entry) => entries.push(entry),
+            Err(e) => return account_error(e),
+        }
+    }
+    entries.extend(files.iter().map(|file| BundleEntry::stored(file.id, format!("files/{}", file.fullname))));
+    entries.extend(photos.iter().map(|photo| {
+        let name = format!("print_photos/{}.{}", photo.id, image_extension(&photo.content_type));
+        BundleEntry::stored(photo.id, name)
+    }));
+    if let Some(avatar) = avatar {
+        entries.push(BundleEntry::stored(avatar.id, format!("avatar.{}", image_extension(&avatar.content_type))));
+    }
+
+    zip_response(data.storage.clone(), entries, &format!("account-{}.zip", user.id))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Account deleted, files only this account owned are transferred or deleted"),
+(status = 400, description = "Unknown user to transfer the files to", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Password missing or wrong, for accounts without password a session older than \
+5 minutes, or called with an API key", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = DeleteAccount),
+)]
+#[delete("/users/me")]
+pub async fn delete_my_account(
+    req: HttpRequest,
+    user: AuthUser,
+    body: web::Json<DeleteAccount>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let body = body.into_inner();
+    let password_hash = match select_password_hash(user.id, &data).await {
+        Ok(Some(password_hash)) => password_hash,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(json!({"status": "fail","message": "User not found"}));
+        }
+        Err(e) => return account_error(e),
+    };
+    if let Some(password_hash) = password_hash {
+        let password = body.password.unwrap_or_default();
+        let valid = web::block(move || password::verify(&password, &password_hash)).await;
+        if !matches!(valid, Ok(true)) {
+            return HttpResponse::Forbidden()
+                .json(json!({"status": "fail","message": "Password is wrong"}));
+        }
+    } else {
+        // without a password, a fresh login at the provider stands in for re-entering it
+        let token_hash = bearer_token(&req).map(token::hash).unwrap_or_default();
+        match is_recent_session(&token_hash, RECENT_LOGIN_MINUTES, &data).await {
+            Ok(true) => {}
+            Ok(false) => {
+                let message = format!(
+                    "Log in again and delete the account within {} minutes",
+                    RECENT_LOGIN_MINUTES
+                );
+                return HttpResponse::Forbidden().json(json!({"status": "fail","message": message}));
+            }
+            Err(e) => return account_error(e),
+        }
+    }
+
+    match delete_account(user.id, body.transfer_files_to, &data).await {
+        Ok(AccountDeletion::Deleted(blobs)) => {
+            for blob in blobs {
+                let _ = data.storage.delete(blob).await;
+            }
+            HttpResponse::NoContent().finish()
+        }
+        Ok(AccountDeletion::NotFound) => HttpResponse::NotFound()
+            .json(json!({"status": "fail","message": "User not found"})),
+        Ok(AccountDeletion::UnknownRecipient) => HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "transferFilesTo must be another existing user"})),
+        Err(e) => account_error(e),
+    }
+}
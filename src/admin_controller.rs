@@ -0,0 +1,300 @@
+use crate::{
+    auth::{AdminUser, AuthUser, ModeratorUser},
+    query_service::gcode_queries::is_file_visible,
+    schema::{CreateAbuseReport, ReportFilterOptions, SuspendUser, UpdateUserRole},
+    AppState, GetIdSchema,
+};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::admin_queries::*;
+
+const MAX_REPORT_REASON_LENGTH: usize = 2000;
+
+fn admin_error(e: impl std::fmt::Debug) -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn user_not_found(user_id: Uuid) -> HttpResponse {
+    let message = format!("User with ID: {} not found", user_id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+fn file_not_found(file_id: Uuid) -> HttpResponse {
+    let message = format!("File with ID: {} not found", file_id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+/// Admins must not lock themselves out, another admin has to do that.
+fn own_account() -> HttpResponse {
+    HttpResponse::BadRequest()
+        .json(json!({"status": "fail","message": "Admins cannot change their own role or suspension"}))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Role changed"),
+(status = 400, description = "Own account", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdateUserRole),
+params(
+("id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/admin/users/{id}/role")]
+pub async fn change_user_role(
+    admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateUserRole>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if user_id == admin.id {
+        return own_account();
+    }
+    match update_user_role(user_id, body.role, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => user_not_found(user_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "User suspended and logged out"),
+(status = 400, description = "Own account", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = SuspendUser),
+params(
+("id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/admin/users/{id}/suspension")]
+pub async fn suspend_user_handler(
+    admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<SuspendUser>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    if user_id == admin.id {
+        return own_account();
+    }
+    let reason = body.reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+    match suspend_user(user_id, reason, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => user_not_found(user_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Suspension lifted"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/admin/users/{id}/suspension")]
+pub async fn unsuspend_user_handler(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    match unsuspend_user(user_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => user_not_found(user_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "File hidden from the public, users it is shared with still see it"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Moderator role required", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/admin/files/{id}/hidden")]
+pub async fn hide_file(
+    _moderator: ModeratorUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    match set_file_hidden(file_id, true, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => file_not_found(file_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "File visible again"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Moderator role required", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/admin/files/{id}/hidden")]
+pub async fn unhide_file(
+    _moderator: ModeratorUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    match set_file_hidden(file_id, false, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => file_not_found(file_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "File deleted with its G-code, prints and print jobs"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/admin/files/{id}")]
+pub async fn delete_any_file_handler(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    match delete_any_file(file_id, &data).await {
+        Ok(Some(blobs)) => {
+            for blob in blobs {
+                let _ = data.storage.delete(blob).await;
+            }
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => file_not_found(file_id),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Reported", body = GetIdSchema),
+(status = 400, description = "Missing or too long reason", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateAbuseReport),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/files/{id}/reports")]
+pub async fn report_file(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<CreateAbuseReport>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    let reason = body.reason.trim();
+    if reason.is_empty() || reason.chars().count() > MAX_REPORT_REASON_LENGTH {
+        let message = format!("reason must have between 1 and {} characters", MAX_REPORT_REASON_LENGTH);
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    match is_file_visible(file_id, Some(user.id), &data).await {
+        Ok(true) => {}
+        Ok(false) => return file_not_found(file_id),
+        Err(e) => return admin_error(e),
+    }
+    match insert_abuse_report(file_id, user.id, reason, &data).await {
+        Ok(id) => HttpResponse::Created().json(GetIdSchema { id }),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, oldest first", body = Vec<AbuseReportModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Moderator role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("resolved" = Option<bool>, Query, description = "Resolved instead of open reports"),
+("page" = Option<usize>, Query, description = "Page, starting at 1"),
+("limit" = Option<usize>, Query, description = "Reports per page, defaults to 10")
+))]
+#[get("/admin/reports")]
+pub async fn get_abuse_reports(
+    _moderator: ModeratorUser,
+    opts: web::Query<ReportFilterOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let limit = opts.limit.unwrap_or(10);
+    let offset = (opts.page.unwrap_or(1).max(1) - 1) * limit;
+    match select_abuse_reports(opts.resolved.unwrap_or(false), limit, offset, &data).await {
+        Ok(reports) => HttpResponse::Ok().json(reports),
+        Err(e) => admin_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Report resolved"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Moderator role required", body = String),
+(status = 404, description = "Report not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Report Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/admin/reports/{id}/resolved")]
+pub async fn resolve_abuse_report_handler(
+    moderator: ModeratorUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let report_id = path.into_inner();
+    match resolve_abuse_report(report_id, moderator.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("Report with ID: {} not found", report_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => admin_error(e),
+    }
+}
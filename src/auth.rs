@@ -0,0 +1,188 @@
+use crate::{
+    model::{ApiKeyScope, UserRole},
+    token, AppState,
+};
+use actix_web::{
+    dev::Payload,
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorUnauthorized},
+    http::{header, Method},
+    web, Error, FromRequest, HttpRequest,
+};
+use futures::future::LocalBoxFuture;
+use serde_json::json;
+use uuid::Uuid;
+
+/// The token of `Authorization: Bearer <token>`, if the request carries one.
+pub fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Tells API keys apart from session tokens in the `Authorization` header.
+pub const API_KEY_PREFIX: &str = "pk_";
+
+/// The scope an API key needs for a request: reading, uploading files, or any other change.
+fn required_scope(req: &HttpRequest) -> ApiKeyScope {
+    let path = req.path();
+    match *req.method() {
+        Method::GET | Method::HEAD => ApiKeyScope::Read,
+        Method::POST if path == "/api/files/download" => ApiKeyScope::Read,
+        Method::POST if path == "/api/files" || path == "/api/files/import" => ApiKeyScope::Upload,
+        Method::POST if path.starts_with("/api/files/") && path.ends_with("/gcode-variants") => {
+            ApiKeyScope::Upload
+        }
+        _ => ApiKeyScope::Manage,
+    }
+}
+
+/// The user on whose behalf a request is made, from the session token or API key in the
+/// `Authorization` header. Suspended accounts are rejected here, so no controller has to
+/// check for them.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthUser {
+    pub id: Uuid,
+    pub role: UserRole,
+    /// Set if the request is authenticated with an API key instead of a session
+    pub api_key_id: Option<Uuid>,
+}
+
+impl AuthUser {
+    pub fn is_api_key(&self) -> bool {
+        self.api_key_id.is_some()
+    }
+}
+
+struct Account {
+    id: Uuid,
+    role: UserRole,
+    suspended: bool,
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let token = bearer_token(req).map(|token| (token.starts_with(API_KEY_PREFIX), token::hash(token)));
+        let scope = required_scope(req);
+        let data = req.app_data::<web::Data<AppState>>().cloned();
+
+        Box::pin(async move {
+            let data = data.ok_or_else(|| ErrorInternalServerError("AppState is not configured"))?;
+            let (account, api_key_id) = match token {
+                Some((true, token_hash)) => api_key_account(&token_hash, scope, &data).await?,
+                Some((false, token_hash)) => (session_account(&token_hash, &data).await?, None),
+                None => {
+                    return Err(ErrorUnauthorized(
+                        json!({"status": "fail","message": "Missing session token or API key"}),
+                    ));
+                }
+            };
+            if account.suspended {
+                return Err(ErrorForbidden(json!({"status": "fail","message": "Account is suspended"})));
+            }
+            Ok(AuthUser { id: account.id, role: account.role, api_key_id })
+        })
+    }
+}
+
+async fn session_account(token_hash: &str, data: &web::Data<AppState>) -> Result<Account, Error> {
+    sqlx::query_as!(
+        Account,
+        r#"SELECT ua.id, ua.role as "role: UserRole", ua.suspended IS NOT NULL as "suspended!"
+         FROM user_session s
+            JOIN user_account ua ON ua.id = s.user_account_fk
+         WHERE s.token_hash = $1 AND s.expires > now()"#,
+        token_hash
+    )
+        .fetch_optional(&data.db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ErrorUnauthorized(json!({"status": "fail","message": "Invalid or expired session token"}))
+        })
+}
+
+/// Looks up an unexpired key, records its use and checks that it grants `scope`.
+async fn api_key_account(
+    token_hash: &str,
+    scope: ApiKeyScope,
+    data: &web::Data<AppState>,
+) -> Result<(Account, Option<Uuid>), Error> {
+    let key = sqlx::query!(
+        r#"UPDATE api_key k SET last_used = now()
+         FROM user_account ua
+         WHERE ua.id = k.user_account_fk
+            AND k.token_hash = $1 AND (k.expires IS NULL OR k.expires > now())
+         RETURNING k.id, k.user_account_fk, k.scopes as "scopes: Vec<ApiKeyScope>",
+            ua.role as "role: UserRole", ua.suspended IS NOT NULL as "suspended!""#,
+        token_hash
+    )
+        .fetch_optional(&data.db)
+        .await
+        .map_err(ErrorInternalServerError)?
+        .ok_or_else(|| {
+            ErrorUnauthorized(json!({"status": "fail","message": "Invalid or expired API key"}))
+        })?;
+
+    if !key.scopes.contains(&scope) {
+        let message = format!("API key lacks the {} scope", format!("{:?}", scope).to_lowercase());
+        return Err(ErrorForbidden(json!({"status": "fail","message": message})));
+    }
+    let account = Account { id: key.user_account_fk, role: key.role, suspended: key.suspended };
+    Ok((account, Some(key.id)))
+}
+
+/// The guard behind [`ModeratorUser`] and [`AdminUser`]: resolves the [`AuthUser`] and
+/// rejects it unless its role is at least `role`.
+fn require_role(
+    req: &HttpRequest,
+    payload: &mut Payload,
+    role: UserRole,
+) -> LocalBoxFuture<'static, Result<AuthUser, Error>> {
+    let user = AuthUser::from_request(req, payload);
+    Box::pin(async move {
+        let user = user.await?;
+        if user.role >= role {
+            Ok(user)
+        } else {
+            let message = format!("{:?} role required", role);
+            Err(ErrorForbidden(json!({"status": "fail","message": message})))
+        }
+    })
+}
+
+/// An [`AuthUser`] with the moderator or admin role.
+#[derive(Debug, Clone, Copy)]
+pub struct ModeratorUser {
+    pub id: Uuid,
+}
+
+impl FromRequest for ModeratorUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = require_role(req, payload, UserRole::Moderator);
+        Box::pin(async move { user.await.map(|user| ModeratorUser { id: user.id }) })
+    }
+}
+
+/// An [`AuthUser`] with the admin role.
+#[derive(Debug, Clone, Copy)]
+pub struct AdminUser {
+    pub id: Uuid,
+}
+
+impl FromRequest for AdminUser {
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let user = require_role(req, payload, UserRole::Admin);
+        Box::pin(async move { user.await.map(|user| AdminUser { id: user.id }) })
+    }
+}
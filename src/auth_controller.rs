@@ -0,0 +1,361 @@
+use crate::{
+    auth::{bearer_token, AuthUser},
+    mailer::Mail,
+    auth::API_KEY_PREFIX,
+    model::{CreatedApiKeyModel, SessionModel},
+    password,
+    query_service::user_queries::normalize_mail,
+    schema::{ChangePassword, CreateApiKey, Login, PasswordResetConfirm, PasswordResetRequest},
+    token, AppState,
+};
+
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::auth_queries::*;
+
+fn auth_error(e: impl std::fmt::Debug) -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+/// For changes to credentials, which an API key must not be able to make.
+pub fn session_required() -> HttpResponse {
+    HttpResponse::Forbidden()
+        .json(json!({"status": "fail","message": "This action requires a session, not an API key"}))
+}
+
+fn weak_password() -> HttpResponse {
+    let message = format!("Password must have at least {} characters", password::MIN_PASSWORD_LENGTH);
+    HttpResponse::BadRequest().json(json!({"status": "fail","message": message}))
+}
+
+/// Hashing is deliberately slow, so it runs off the async workers.
+async fn hash_password(new_password: String) -> Result<String, HttpResponse> {
+    match web::block(move || password::hash(&new_password)).await {
+        Ok(Ok(password_hash)) => Ok(password_hash),
+        Ok(Err(e)) => Err(auth_error(e)),
+        Err(e) => Err(auth_error(e)),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created, send the token as `Authorization: Bearer <token>`", body = SessionModel),
+(status = 401, description = "Unknown or unverified mail, or wrong password", body = String),
+(status = 403, description = "Account is suspended", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = Login),
+)]
+#[post("/sessions")]
+pub async fn login(
+    body: web::Json<Login>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let body = body.into_inner();
+    let credentials = match normalize_mail(&body.mail) {
+        Some(mail) => select_credentials_by_mail(&mail, &data).await,
+        None => Ok(None),
+    };
+    let credentials = match credentials {
+        Ok(credentials) => credentials,
+        Err(e) => return auth_error(e),
+    };
+    let (user_id, password_hash, suspended) = match credentials {
+        Some(Credentials { user_id, password_hash: Some(password_hash), suspended }) => {
+            (user_id, password_hash, suspended)
+        }
+        _ => {
+            return HttpResponse::Unauthorized()
+                .json(json!({"status": "fail","message": "Wrong mail or password"}));
+        }
+    };
+    let valid = web::block(move || password::verify(&body.password, &password_hash)).await;
+    if !matches!(valid, Ok(true)) {
+        return HttpResponse::Unauthorized()
+            .json(json!({"status": "fail","message": "Wrong mail or password"}));
+    }
+    // only answered after the password check, so it does not reveal which accounts exist
+    if suspended {
+        return HttpResponse::Forbidden()
+            .json(json!({"status": "fail","message": "Account is suspended"}));
+    }
+
+    let session_token = token::generate();
+    match insert_session(user_id, &token::hash(&session_token), &data).await {
+        Ok(expires) => HttpResponse::Created().json(SessionModel { token: session_token, expires }),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Session ended"),
+(status = 401, description = "No session token", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[delete("/sessions/current")]
+pub async fn logout(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let session_token = match bearer_token(&req) {
+        Some(session_token) => session_token,
+        None => {
+            return HttpResponse::Unauthorized()
+                .json(json!({"status": "fail","message": "Missing session token"}));
+        }
+    };
+    match delete_session(&token::hash(session_token), &data).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Password changed, other sessions are ended"),
+(status = 400, description = "Password too short", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Current password wrong, no password set yet (use the password reset, which \
+proves control of a verified mail), or called with an API key", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = ChangePassword),
+)]
+#[put("/users/me/password")]
+pub async fn change_password(
+    user: AuthUser,
+    req: HttpRequest,
+    body: web::Json<ChangePassword>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let body = body.into_inner();
+    if !password::is_acceptable(&body.new_password) {
+        return weak_password();
+    }
+    let current_hash = match select_password_hash(user.id, &data).await {
+        Ok(Some(current_hash)) => current_hash,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(json!({"status": "fail","message": "User not found"}));
+        }
+        Err(e) => return auth_error(e),
+    };
+    // a first password is only set through the reset flow, which proves control of a verified mail
+    let current_hash = match current_hash {
+        Some(current_hash) => current_hash,
+        None => {
+            return HttpResponse::Forbidden()
+                .json(json!({"status": "fail","message": "No password set yet, use the password reset to set one"}));
+        }
+    };
+    let current_password = body.current_password;
+    let valid = web::block(move || password::verify(&current_password, &current_hash)).await;
+    if !matches!(valid, Ok(true)) {
+        return HttpResponse::Forbidden()
+            .json(json!({"status": "fail","message": "Current password is wrong"}));
+    }
+
+    let password_hash = match hash_password(body.new_password).await {
+        Ok(password_hash) => password_hash,
+        Err(response) => return response,
+    };
+    let keep_session = bearer_token(&req).map(token::hash);
+    match update_password(user.id, &password_hash, keep_session.as_deref(), &data).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 202, description = "If the mail is verified, a reset link is sent to it"),
+(status = 429, description = "Too many reset requests for this mail", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = PasswordResetRequest),
+)]
+#[post("/password-reset")]
+pub async fn request_password_reset(
+    body: web::Json<PasswordResetRequest>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // unknown and invalid mails get the same answer, so the endpoint does not reveal accounts
+    let mail = match normalize_mail(&body.mail) {
+        Some(mail) => mail,
+        None => return HttpResponse::Accepted().finish(),
+    };
+    match record_reset_request(&mail, &data).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::TooManyRequests()
+                .json(json!({"status": "fail","message": "Too many reset requests, try again later"}));
+        }
+        Err(e) => return auth_error(e),
+    }
+
+    let reset_token = token::generate();
+    match insert_reset_token(&mail, &token::hash(&reset_token), &data).await {
+        Ok(true) => send_reset(&mail, &reset_token, &data).await,
+        Ok(false) => {}
+        Err(e) => return auth_error(e),
+    }
+    HttpResponse::Accepted().finish()
+}
+
+async fn send_reset(mail: &str, reset_token: &str, data: &web::Data<AppState>) {
+    let public_url = std::env::var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+    let message = Mail {
+        to: mail.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Use this token to choose a new password at {}/api/password-reset/confirm:\n\n{}\n\n\
+             The token expires in {} minutes. If you did not ask for a reset, ignore this mail.",
+            public_url.trim_end_matches('/'),
+            reset_token,
+            RESET_TOKEN_TTL_MINUTES
+        ),
+    };
+    if let Err(e) = data.mailer.send(&message).await {
+        println!("🔥 Password reset mail to {} failed: {}", mail, e);
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Password changed, all sessions are ended"),
+(status = 400, description = "Invalid, used or expired token, or password too short", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = PasswordResetConfirm),
+)]
+#[post("/password-reset/confirm")]
+pub async fn confirm_password_reset(
+    body: web::Json<PasswordResetConfirm>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let body = body.into_inner();
+    if !password::is_acceptable(&body.new_password) {
+        return weak_password();
+    }
+    let password_hash = match hash_password(body.new_password).await {
+        Ok(password_hash) => password_hash,
+        Err(response) => return response,
+    };
+    match reset_password(&token::hash(&body.token), &password_hash, &data).await {
+        Ok(PasswordReset::Reset) => HttpResponse::NoContent().finish(),
+        Ok(PasswordReset::Expired) => HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Reset token expired, request a new one"})),
+        Ok(PasswordReset::Invalid) => HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Invalid reset token"})),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created, send the token as `Authorization: Bearer <token>`", body = CreatedApiKeyModel),
+(status = 400, description = "Missing name or scopes, or expiry in the past", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 409, description = "A key with this name exists", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateApiKey),
+)]
+#[post("/users/me/api-keys")]
+pub async fn create_api_key(
+    user: AuthUser,
+    body: web::Json<CreateApiKey>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let name = body.name.trim();
+    let message = if name.is_empty() || name.chars().count() > 100 {
+        Some("name must have between 1 and 100 characters")
+    } else if body.scopes.is_empty() {
+        Some("scopes must not be empty")
+    } else if body.expires.is_some_and(|expires| expires <= chrono::Utc::now()) {
+        Some("expires must be in the future")
+    } else {
+        None
+    };
+    if let Some(message) = message {
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+
+    let api_key_token = format!("{}{}", API_KEY_PREFIX, token::generate());
+    match insert_api_key(user.id, &body, &token::hash(&api_key_token), &data).await {
+        Ok(api_key) => HttpResponse::Created().json(CreatedApiKeyModel { token: api_key_token, api_key }),
+        Err(e) if e.to_string().contains("duplicate key") => HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "An API key with this name already exists"})),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the keys without their tokens", body = Vec<ApiKeyModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/users/me/api-keys")]
+pub async fn get_api_keys(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    match select_api_keys(user.id, &data).await {
+        Ok(api_keys) => HttpResponse::Ok().json(api_keys),
+        Err(e) => auth_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Key revoked"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 404, description = "Key not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "API key Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/users/me/api-keys/{id}")]
+pub async fn revoke_api_key(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let id = path.into_inner();
+    match delete_api_key(id, user.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("API key with ID: {} not found", id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => auth_error(e),
+    }
+}
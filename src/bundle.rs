@@ -0,0 +1,217 @@
+use crate::{model::DownloadableFile, storage::Storage};
+use actix_web::{http::header, web::Bytes, HttpResponse};
+use std::collections::HashSet;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use zip::{result::{ZipError, ZipResult}, write::SimpleFileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+pub const MAX_IMPORT_BYTES: usize = 256 * 1024 * 1024;
+/// Limits what a small archive can unpack to, all entries together.
+const MAX_UNPACKED_BYTES: u64 = 512 * 1024 * 1024;
+const MAX_IMPORT_ENTRIES: usize = 1000;
+const GCODE_EXTENSIONS: [&str; 3] = ["gcode", "gco", "g"];
+
+pub enum EntrySource {
+    /// A blob in storage, skipped if it is missing
+    Stored(Uuid),
+    Inline(Vec<u8>),
+}
+
+pub struct BundleEntry {
+    pub name: String,
+    pub source: EntrySource,
+}
+
+impl BundleEntry {
+    pub fn stored(id: Uuid, name: impl Into<String>) -> Self {
+        BundleEntry { name: name.into(), source: EntrySource::Stored(id) }
+    }
+
+    pub fn inline(name: impl Into<String>, content: Vec<u8>) -> Self {
+        BundleEntry { name: name.into(), source: EntrySource::Inline(content) }
+    }
+}
+
+impl From<DownloadableFile> for BundleEntry {
+    fn from(file: DownloadableFile) -> Self {
+        BundleEntry::stored(file.id, file.fullname)
+    }
+}
+
+/// Forwards everything written to it as body chunks of a streaming response.
+struct ChannelWriter {
+    tx: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tx
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Streams a ZIP archive of the given entries. The archive is built on a blocking
+/// thread while the client reads it, so it is never held in memory as a whole.
+pub fn zip_response(storage: Storage, entries: Vec<BundleEntry>, filename: &str) -> HttpResponse {
+    zip_response_then(storage, entries, filename, |_| async {})
+}
+
+/// Like `zip_response`, and once the archive is written, passes `written` the ids of the
+/// stored entries whose content made it into the archive. Missing blobs are not among them.
+pub fn zip_response_then<F, Fut>(
+    storage: Storage,
+    entries: Vec<BundleEntry>,
+    filename: &str,
+    written: F,
+) -> HttpResponse
+where
+    F: FnOnce(Vec<Uuid>) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    let (tx, rx) = mpsc::channel::<io::Result<Bytes>>(16);
+    let writer = tokio::task::spawn_blocking(move || {
+        let error_tx = tx.clone();
+        let mut stored = Vec::new();
+        if let Err(e) = write_zip(&storage, entries, ChannelWriter { tx }, &mut stored) {
+            let _ = error_tx.blocking_send(Err(e));
+        }
+        stored
+    });
+    actix_web::rt::spawn(async move {
+        if let Ok(stored) = writer.await {
+            written(stored).await;
+        }
+    });
+    let body = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}\"", filename),
+        ))
+        .streaming(body)
+}
+
+fn write_zip(
+    storage: &Storage,
+    entries: Vec<BundleEntry>,
+    writer: ChannelWriter,
+    stored: &mut Vec<Uuid>,
+) -> io::Result<()> {
+    let mut zip = ZipWriter::new_stream(BufWriter::with_capacity(CHUNK_SIZE, writer));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut names = HashSet::new();
+
+    for entry in entries {
+        match entry.source {
+            EntrySource::Stored(id) => {
+                let mut blob = match File::open(storage.path(id)) {
+                    Ok(blob) => blob,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e),
+                };
+                zip.start_file(unique_name(&mut names, entry.name, &id.to_string()), options)?;
+                io::copy(&mut blob, &mut zip)?;
+                stored.push(id);
+            }
+            EntrySource::Inline(content) => {
+                let suffix = names.len().to_string();
+                zip.start_file(unique_name(&mut names, entry.name, &suffix), options)?;
+                zip.write_all(&content)?;
+            }
+        }
+    }
+
+    zip.finish()?.flush()
+}
+
+/// Two files may share a `fullname`, but entries in one archive must not.
+fn unique_name(names: &mut HashSet<String>, name: String, suffix: &str) -> String {
+    let name = if names.contains(&name) {
+        format!("{}-{}", name, suffix)
+    } else {
+        name
+    };
+    names.insert(name.clone());
+    name
+}
+
+pub struct ImportEntry {
+    pub name: String,
+    pub content: Result<Vec<u8>, String>,
+}
+
+impl ImportEntry {
+    pub fn is_gcode(&self) -> bool {
+        self.name
+            .rsplit_once('.')
+            .map(|(_, ext)| GCODE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+            .unwrap_or(false)
+    }
+}
+
+/// Unpacks every regular file of an uploaded archive. Directory structure is dropped, so
+/// entries are named by their file name only. Unreadable or oversized entries are kept
+/// with an error so they can be reported back individually, as are all entries once the
+/// archive has unpacked to `MAX_UNPACKED_BYTES`. Archives with more than
+/// `MAX_IMPORT_ENTRIES` entries are rejected as a whole.
+pub fn read_zip(bytes: Bytes) -> ZipResult<Vec<ImportEntry>> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    if archive.len() > MAX_IMPORT_ENTRIES {
+        return Err(ZipError::InvalidArchive(
+            format!("more than {} entries", MAX_IMPORT_ENTRIES).into(),
+        ));
+    }
+    let mut entries = Vec::with_capacity(archive.len());
+    let mut unpacked: u64 = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file
+            .enclosed_name()
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| file.name().to_string());
+        // the sizes in the archive can lie, so reading stops one byte past what is allowed
+        let limit = (MAX_IMPORT_BYTES as u64).min(MAX_UNPACKED_BYTES - unpacked);
+        let content = if file.size() > limit {
+            Err(too_large(limit))
+        } else {
+            let mut content = Vec::with_capacity(file.size() as usize);
+            match file.by_ref().take(limit + 1).read_to_end(&mut content) {
+                Ok(read) if read as u64 > limit => Err(too_large(limit)),
+                Ok(read) => {
+                    unpacked += read as u64;
+                    Ok(content)
+                }
+                Err(e) => Err(e.to_string()),
+            }
+        };
+        entries.push(ImportEntry { name, content });
+    }
+
+    Ok(entries)
+}
+
+fn too_large(limit: u64) -> String {
+    if limit < MAX_IMPORT_BYTES as u64 {
+        format!("Archive exceeds {} bytes unpacked", MAX_UNPACKED_BYTES)
+    } else {
+        format!("Entry exceeds {} bytes", MAX_IMPORT_BYTES)
+    }
+}
@@ -0,0 +1,519 @@
+use crate::{
+    auth::AdminUser,
+    schema::{CatalogSearchOptions, CreateBrand, CreateMaterial, CreatePrinter, UpdateMaterial, UpdatePrinter},
+    AppState,
+};
+
+use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::catalog_queries::*;
+
+fn catalog_error(e: sqlx::Error) -> HttpResponse {
+    let message = e.to_string();
+    if message.contains("duplicate key value violates unique constraint") {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Entry with that name already exists"}));
+    }
+    if message.contains("violates foreign key constraint") {
+        return HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Referenced brand does not exist or entry is still in use"}),
+        );
+    }
+    if message.contains("violates check constraint") {
+        return HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Density and diameter must be positive, price must not be negative"}),
+        );
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn not_found(kind: &str, id: Uuid) -> HttpResponse {
+    let message = format!("{} with ID: {} not found", kind, id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+fn empty_name(field: &str) -> HttpResponse {
+    let message = format!("{} must not be empty", field);
+    HttpResponse::BadRequest().json(json!({"status": "fail","message": message}))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<BrandModel>),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("q" = Option<String>, Query, description = "Search term matched against the brand name")
+))]
+#[get("/printer-brands")]
+pub async fn get_printer_brands(
+    opts: web::Query<CatalogSearchOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_printer_brands(&opts, &data).await {
+        Ok(brands) => HttpResponse::Ok().json(brands),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = BrandModel),
+(status = 400, description = "Empty or duplicate name", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateBrand),
+)]
+#[post("/printer-brands")]
+pub async fn create_printer_brand(
+    _admin: AdminUser,
+    body: web::Json<CreateBrand>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if body.full_name.trim().is_empty() {
+        return empty_name("fullName");
+    }
+    match insert_printer_brand(body.full_name.trim(), &data).await {
+        Ok(brand) => HttpResponse::Created().json(brand),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = BrandModel),
+(status = 400, description = "Empty or duplicate name", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Printer brand not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateBrand),
+params(
+("id" = String, Path, description = "Printer brand Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/printer-brands/{id}")]
+pub async fn edit_printer_brand(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<CreateBrand>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if body.full_name.trim().is_empty() {
+        return empty_name("fullName");
+    }
+    match update_printer_brand(id, body.full_name.trim(), &data).await {
+        Ok(Some(brand)) => HttpResponse::Ok().json(brand),
+        Ok(None) => not_found("Printer brand", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 400, description = "Printer brand is still in use", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Printer brand not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Printer brand Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/printer-brands/{id}")]
+pub async fn remove_printer_brand(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match delete_printer_brand(id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => not_found("Printer brand", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<BrandModel>),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("q" = Option<String>, Query, description = "Search term matched against the brand name")
+))]
+#[get("/material-brands")]
+pub async fn get_material_brands(
+    opts: web::Query<CatalogSearchOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_material_brands(&opts, &data).await {
+        Ok(brands) => HttpResponse::Ok().json(brands),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = BrandModel),
+(status = 400, description = "Empty or duplicate name", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateBrand),
+)]
+#[post("/material-brands")]
+pub async fn create_material_brand(
+    _admin: AdminUser,
+    body: web::Json<CreateBrand>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if body.full_name.trim().is_empty() {
+        return empty_name("fullName");
+    }
+    match insert_material_brand(body.full_name.trim(), &data).await {
+        Ok(brand) => HttpResponse::Created().json(brand),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = BrandModel),
+(status = 400, description = "Empty or duplicate name", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Material brand not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateBrand),
+params(
+("id" = String, Path, description = "Material brand Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/material-brands/{id}")]
+pub async fn edit_material_brand(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<CreateBrand>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if body.full_name.trim().is_empty() {
+        return empty_name("fullName");
+    }
+    match update_material_brand(id, body.full_name.trim(), &data).await {
+        Ok(Some(brand)) => HttpResponse::Ok().json(brand),
+        Ok(None) => not_found("Material brand", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 400, description = "Material brand is still in use", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Material brand not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Material brand Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/material-brands/{id}")]
+pub async fn remove_material_brand(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match delete_material_brand(id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => not_found("Material brand", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<PrinterCatalogModel>),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("q" = Option<String>, Query, description = "Search term matched against brand and name"),
+("brandId" = Option<String>, Query, description = "Only entries of this brand")
+))]
+#[get("/printers")]
+pub async fn get_printers(
+    opts: web::Query<CatalogSearchOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_printers(&opts, &data).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrinterCatalogModel),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/printers/{id}")]
+pub async fn get_printer(
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match select_printer(id, &data).await {
+        Ok(Some(entry)) => HttpResponse::Ok().json(entry),
+        Ok(None) => not_found("Printer", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = PrinterCatalogModel),
+(status = 400, description = "Empty or duplicate entry, or unknown brand", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreatePrinter, description="all parameters are required"),
+)]
+#[post("/printers")]
+pub async fn create_printer(
+    _admin: AdminUser,
+    body: web::Json<CreatePrinter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if body.model.trim().is_empty() {
+        return empty_name("model");
+    }
+    let query_result = match insert_printer(&body, &data).await {
+        Ok(id) => select_printer(id, &data).await,
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(entry)) => HttpResponse::Created().json(entry),
+        Ok(None) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "Created entry could not be read"})),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrinterCatalogModel),
+(status = 400, description = "Empty or duplicate entry, or unknown brand", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdatePrinter, description="only supplied parameters are changed"),
+params(
+("id" = String, Path, description = "Printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/printers/{id}")]
+pub async fn edit_printer(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdatePrinter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if body.model.as_deref().is_some_and(|value| value.trim().is_empty()) {
+        return empty_name("model");
+    }
+    let query_result = match update_printer(id, &body, &data).await {
+        Ok(true) => select_printer(id, &data).await,
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(entry)) => HttpResponse::Ok().json(entry),
+        Ok(None) => not_found("Printer", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 400, description = "Printer is still in use", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/printers/{id}")]
+pub async fn remove_printer(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match delete_printer(id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => not_found("Printer", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<MaterialCatalogModel>),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("q" = Option<String>, Query, description = "Search term matched against brand and name"),
+("brandId" = Option<String>, Query, description = "Only entries of this brand"),
+("matType" = Option<MaterialType>, Query, description = "Only materials of this type")
+))]
+#[get("/materials")]
+pub async fn get_materials(
+    opts: web::Query<CatalogSearchOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_materials(&opts, &data).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = MaterialCatalogModel),
+(status = 404, description = "Material not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Material Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/materials/{id}")]
+pub async fn get_material(
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match select_material(id, &data).await {
+        Ok(Some(entry)) => HttpResponse::Ok().json(entry),
+        Ok(None) => not_found("Material", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = MaterialCatalogModel),
+(status = 400, description = "Empty or duplicate entry, unknown brand or invalid physical property", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateMaterial, description="description, matType and brandId are required"),
+)]
+#[post("/materials")]
+pub async fn create_material(
+    _admin: AdminUser,
+    body: web::Json<CreateMaterial>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if body.description.trim().is_empty() {
+        return empty_name("description");
+    }
+    let query_result = match insert_material(&body, &data).await {
+        Ok(id) => select_material(id, &data).await,
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(entry)) => HttpResponse::Created().json(entry),
+        Ok(None) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "Created entry could not be read"})),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = MaterialCatalogModel),
+(status = 400, description = "Empty or duplicate entry, unknown brand or invalid physical property", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Material not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdateMaterial, description="only supplied parameters are changed"),
+params(
+("id" = String, Path, description = "Material Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/materials/{id}")]
+pub async fn edit_material(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateMaterial>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    if body.description.as_deref().is_some_and(|value| value.trim().is_empty()) {
+        return empty_name("description");
+    }
+    let query_result = match update_material(id, &body, &data).await {
+        Ok(true) => select_material(id, &data).await,
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(entry)) => HttpResponse::Ok().json(entry),
+        Ok(None) => not_found("Material", id),
+        Err(e) => catalog_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 400, description = "Material is still in use", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 404, description = "Material not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Material Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/materials/{id}")]
+pub async fn remove_material(
+    _admin: AdminUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let id = path.into_inner();
+    match delete_material(id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => not_found("Material", id),
+        Err(e) => catalog_error(e),
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    auth::AuthUser,
+    files_controller::download_response,
+    schema::CreateCollection,
+    AppState,
+};
+
+use actix_web::{get, post, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::collection_queries::*;
+use crate::query_service::file_queries::select_downloadable_files;
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = IdSchema),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateCollection, description="all parameters are required"),
+)]
+#[post("/collections")]
+pub async fn create_collection(
+    user: AuthUser,
+    body: web::Json<CreateCollection>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match insert_collection(&body, user.id, data).await {
+        Ok(collection) => HttpResponse::Created().json(collection),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "ZIP archive of all files in the collection the caller may download", content_type = "application/zip"),
+(status = 404, description = "Collection not found or nothing downloadable", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Collection Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/collections/{id}/download")]
+pub async fn download_collection(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let collection_id = path.into_inner();
+    let file_ids = match select_collection_file_ids(collection_id, &data).await {
+        Ok(Some(file_ids)) => file_ids,
+        Ok(None) => {
+            let message = format!("Collection with ID: {} not found", collection_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+
+    match select_downloadable_files(&file_ids, user.map(|user| user.id), &data).await {
+        Ok(files) if files.is_empty() => HttpResponse::NotFound()
+            .json(json!({"status": "fail","message": "No downloadable files found"})),
+        Ok(files) => download_response(files, &format!("collection-{}.zip", collection_id), &data),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
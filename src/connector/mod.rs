@@ -0,0 +1,303 @@
+pub mod moonraker;
+pub mod octoprint;
+
+use crate::{
+    model::{ConnectorKind, JobStatus},
+    query_service::queue_queries::{
+        select_connected_printing_jobs, update_job_progress, update_job_status, ConnectorConfig,
+    },
+    schema::UpdatePrintJobStatus,
+    AppState,
+};
+use actix_web::web;
+use std::fmt;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// State of the job on the printer host, normalised across connector kinds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteState {
+    Idle,
+    Printing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteJob {
+    pub state: RemoteState,
+    /// Completion in percent
+    pub progress: Option<f32>,
+    /// File of the current or last job, as named on the host
+    pub filename: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ConnectorError {
+    Http(reqwest::Error),
+    Unexpected(String),
+}
+
+impl fmt::Display for ConnectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectorError::Http(e) => write!(f, "printer host request failed: {}", e),
+            ConnectorError::Unexpected(message) => write!(f, "unexpected printer host response: {}", message),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ConnectorError {
+    fn from(e: reqwest::Error) -> Self {
+        ConnectorError::Http(e)
+    }
+}
+
+/// Talks to the HTTP API of the host driving a printer.
+pub struct Connector {
+    kind: ConnectorKind,
+    base_url: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl Connector {
+    pub fn new(config: ConnectorConfig) -> Self {
+        Connector {
+            kind: config.kind,
+            base_url: config.url.trim_end_matches('/').to_string(),
+            api_key: config.api_key,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(60))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.header("X-Api-Key", api_key),
+            None => request,
+        }
+    }
+
+    /// Uploads the G-code and starts printing it right away.
+    pub async fn start_print(&self, filename: &str, content: Vec<u8>) -> Result<(), ConnectorError> {
+        match self.kind {
+            ConnectorKind::OctoPrint => octoprint::start_print(self, filename, content).await,
+            ConnectorKind::Moonraker => moonraker::start_print(self, filename, content).await,
+        }
+    }
+
+    pub async fn job(&self) -> Result<RemoteJob, ConnectorError> {
+        match self.kind {
+            ConnectorKind::OctoPrint => octoprint::job(self).await,
+            ConnectorKind::Moonraker => moonraker::job(self).await,
+        }
+    }
+}
+
+fn file_part(filename: &str, content: Vec<u8>) -> Result<reqwest::multipart::Part, ConnectorError> {
+    let filename = if filename.contains('.') {
+        filename.to_string()
+    } else {
+        format!("{}.gcode", filename)
+    };
+    reqwest::multipart::Part::bytes(content)
+        .file_name(filename)
+        .mime_str("application/octet-stream")
+        .map_err(ConnectorError::from)
+}
+
+/// Name the G-code of a job is uploaded as. The job id makes it unique, so a host reporting
+/// this file is reporting this job even if it finished between two polls.
+pub fn job_filename(job_id: Uuid, filename: &str) -> String {
+    format!("{}-{}", job_id.simple(), filename)
+}
+
+/// Hosts may sanitize the rest of the name, the job id survives.
+fn is_job_file(job_id: Uuid, remote: &RemoteJob) -> bool {
+    remote
+        .filename
+        .as_deref()
+        .is_some_and(|filename| filename.contains(&job_id.simple().to_string()))
+}
+
+/// The status a job ends with, `None` while it is not over. Unless the host has reported the
+/// job printing or reports the file uploaded for it, a finished or cancelled state is left over
+/// from the job before it.
+fn finished_status(remote: &RemoteJob, job_id: Uuid, started: bool) -> Option<JobStatus> {
+    let current = started || is_job_file(job_id, remote);
+    match remote.state {
+        RemoteState::Idle | RemoteState::Printing => None,
+        RemoteState::Done | RemoteState::Cancelled if !current => None,
+        RemoteState::Done => Some(JobStatus::Done),
+        RemoteState::Failed => Some(JobStatus::Failed),
+        RemoteState::Cancelled => Some(JobStatus::Cancelled),
+    }
+}
+
+/// Checks every job printing on a connected printer once and writes finished jobs back.
+pub async fn poll_printers(data: &web::Data<AppState>) -> Result<(), sqlx::Error> {
+    for printing in select_connected_printing_jobs(data).await? {
+        let remote = match Connector::new(printing.config).job().await {
+            Ok(remote) => remote,
+            Err(e) => {
+                println!("🔥 Polling job {} failed: {}", printing.job_id, e);
+                continue;
+            }
+        };
+
+        if remote.state == RemoteState::Printing {
+            // a stored progress marks the job as started, also for hosts that report none
+            update_job_progress(printing.job_id, Some(remote.progress.unwrap_or(0.0)), data).await?;
+            continue;
+        }
+        let status = match finished_status(&remote, printing.job_id, printing.started) {
+            Some(status) => status,
+            None => continue,
+        };
+        update_job_progress(printing.job_id, remote.progress, data).await?;
+        let update = UpdatePrintJobStatus {
+            status,
+            nozzle_size_mm: None,
+            bed_temp_celsius: None,
+            extruder_temp: None,
+            failure_category: None,
+            notes: None,
+        };
+        update_job_status(printing.job_id, None, &update, data).await?;
+    }
+    Ok(())
+}
+
+pub async fn run_poller(data: web::Data<AppState>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = poll_printers(&data).await {
+            println!("🔥 Polling printer connectors failed: {:?}", e);
+        }
+    }
+}
+
+/// Stand-ins for printer hosts, served by actix on a free local port.
+#[cfg(test)]
+mod mock_host {
+    use super::Connector;
+    use crate::{model::ConnectorKind, query_service::queue_queries::ConnectorConfig};
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use std::sync::{Arc, Mutex};
+
+    pub const API_KEY: &str = "test-key";
+
+    /// An upload received by the mock, with the `X-Api-Key` it was sent with.
+    #[derive(Default)]
+    pub struct Upload {
+        pub api_key: Option<String>,
+        pub body: String,
+    }
+
+    fn connector(kind: ConnectorKind, url: String) -> Connector {
+        Connector::new(ConnectorConfig { kind, url, api_key: Some(API_KEY.to_string()) })
+    }
+
+    /// Answers GET requests to `path` with `body`.
+    pub async fn job_host(kind: ConnectorKind, path: &'static str, body: serde_json::Value) -> Connector {
+        let server = HttpServer::new(move || {
+            let body = body.clone();
+            App::new().route(path, web::get().to(move || {
+                let body = body.clone();
+                async move { HttpResponse::Ok().json(body) }
+            }))
+        })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .expect("mock host binds");
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        connector(kind, url)
+    }
+
+    /// Records the request POSTed to `path` and answers with `status`.
+    pub async fn upload_host(
+        kind: ConnectorKind,
+        path: &'static str,
+        status: u16,
+    ) -> (Connector, Arc<Mutex<Upload>>) {
+        let upload = Arc::new(Mutex::new(Upload::default()));
+        let received = upload.clone();
+        let server = HttpServer::new(move || {
+            let received = received.clone();
+            App::new().route(path, web::post().to(move |req: HttpRequest, body: web::Bytes| {
+                let received = received.clone();
+                async move {
+                    let mut upload = received.lock().unwrap();
+                    upload.api_key = req.headers().get("X-Api-Key")
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_string);
+                    upload.body = String::from_utf8_lossy(&body).into_owned();
+                    HttpResponse::build(actix_web::http::StatusCode::from_u16(status).unwrap()).finish()
+                }
+            }))
+        })
+            .workers(1)
+            .bind(("127.0.0.1", 0))
+            .expect("mock host binds");
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        (connector(kind, url), upload)
+    }
+
+    /// Whether a multipart body has the text field `name` set to `value`.
+    pub fn has_field(body: &str, name: &str, value: &str) -> bool {
+        body.split("--")
+            .any(|part| part.contains(&format!("name=\"{}\"", name)) && part.trim_end().ends_with(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(state: RemoteState, filename: Option<&str>) -> RemoteJob {
+        RemoteJob { state, progress: None, filename: filename.map(str::to_string) }
+    }
+
+    #[test]
+    fn finished_states_count_once_the_job_started() {
+        let job_id = Uuid::new_v4();
+        let finished = |state| finished_status(&remote(state, None), job_id, true);
+        assert_eq!(finished(RemoteState::Done), Some(JobStatus::Done));
+        assert_eq!(finished(RemoteState::Cancelled), Some(JobStatus::Cancelled));
+        assert_eq!(finished(RemoteState::Failed), Some(JobStatus::Failed));
+        assert_eq!(finished(RemoteState::Printing), None);
+        assert_eq!(finished(RemoteState::Idle), None);
+    }
+
+    #[test]
+    fn leftover_states_do_not_finish_a_job_that_has_not_started() {
+        let job_id = Uuid::new_v4();
+        let finished = |state| finished_status(&remote(state, Some("benchy.gcode")), job_id, false);
+        assert_eq!(finished(RemoteState::Done), None);
+        assert_eq!(finished(RemoteState::Cancelled), None);
+        assert_eq!(finished(RemoteState::Failed), Some(JobStatus::Failed));
+    }
+
+    #[test]
+    fn job_file_finishes_a_job_that_ended_between_polls() {
+        let job_id = Uuid::new_v4();
+        let uploaded = job_filename(job_id, "my benchy.gcode");
+        let sanitized = uploaded.replace(' ', "_");
+        for filename in [uploaded.as_str(), sanitized.as_str()] {
+            let done = remote(RemoteState::Done, Some(filename));
+            assert_eq!(finished_status(&done, job_id, false), Some(JobStatus::Done), "{}", filename);
+            let cancelled = remote(RemoteState::Cancelled, Some(filename));
+            assert_eq!(finished_status(&cancelled, job_id, false), Some(JobStatus::Cancelled));
+        }
+        let other_job = remote(RemoteState::Done, Some(&job_filename(Uuid::new_v4(), "my benchy.gcode")));
+        assert_eq!(finished_status(&other_job, job_id, false), None);
+    }
+}
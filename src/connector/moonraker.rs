@@ -0,0 +1,140 @@
+use super::{file_part, Connector, ConnectorError, RemoteJob, RemoteState};
+use reqwest::Method;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    result: QueryResult,
+}
+
+#[derive(Deserialize)]
+struct QueryResult {
+    status: PrinterObjects,
+}
+
+#[derive(Deserialize)]
+struct PrinterObjects {
+    print_stats: PrintStats,
+    virtual_sdcard: Option<VirtualSdcard>,
+}
+
+#[derive(Deserialize)]
+struct PrintStats {
+    state: String,
+    /// Empty while no file was printed since the host started
+    filename: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VirtualSdcard {
+    progress: Option<f32>,
+}
+
+/// `POST /server/files/upload` with `print=true` uploads the file and starts the job.
+pub async fn start_print(
+    connector: &Connector,
+    filename: &str,
+    content: Vec<u8>,
+) -> Result<(), ConnectorError> {
+    let form = reqwest::multipart::Form::new()
+        .part("file", file_part(filename, content)?)
+        .text("root", "gcodes")
+        .text("print", "true");
+    connector
+        .request(Method::POST, "/server/files/upload")
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub async fn job(connector: &Connector) -> Result<RemoteJob, ConnectorError> {
+    let response: QueryResponse = connector
+        .request(Method::GET, "/printer/objects/query?print_stats&virtual_sdcard")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let status = response.result.status;
+    let progress = status
+        .virtual_sdcard
+        .and_then(|sdcard| sdcard.progress)
+        .map(|progress| progress * 100.0);
+
+    let filename = status.print_stats.filename.filter(|filename| !filename.is_empty());
+    let state = match status.print_stats.state.as_str() {
+        "standby" => RemoteState::Idle,
+        "printing" | "paused" => RemoteState::Printing,
+        "complete" => RemoteState::Done,
+        "cancelled" => RemoteState::Cancelled,
+        "error" => RemoteState::Failed,
+        state => return Err(ConnectorError::Unexpected(format!("print state {}", state))),
+    };
+    Ok(RemoteJob { state, progress, filename })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector::mock_host::{has_field, job_host, upload_host, API_KEY};
+    use super::*;
+    use crate::model::ConnectorKind;
+    use serde_json::json;
+
+    async fn job_in(state: &str, progress: Option<f32>) -> Result<RemoteJob, ConnectorError> {
+        let body = json!({"result": {"status": {
+            "print_stats": {"state": state, "filename": "benchy.gcode"},
+            "virtual_sdcard": {"progress": progress}
+        }}});
+        let connector = job_host(ConnectorKind::Moonraker, "/printer/objects/query", body).await;
+        job(&connector).await
+    }
+
+    #[actix_web::test]
+    async fn maps_print_states() {
+        let cases = [
+            ("standby", RemoteState::Idle),
+            ("printing", RemoteState::Printing),
+            ("paused", RemoteState::Printing),
+            ("complete", RemoteState::Done),
+            ("cancelled", RemoteState::Cancelled),
+            ("error", RemoteState::Failed),
+        ];
+        for (state, expected) in cases {
+            assert_eq!(job_in(state, Some(0.5)).await.expect("job is read").state, expected, "{}", state);
+        }
+    }
+
+    #[actix_web::test]
+    async fn reports_progress_in_percent() {
+        assert_eq!(job_in("printing", Some(0.25)).await.unwrap().progress, Some(25.0));
+        assert_eq!(job_in("printing", None).await.unwrap().progress, None);
+    }
+
+    #[actix_web::test]
+    async fn reads_the_printed_file() {
+        assert_eq!(job_in("complete", Some(1.0)).await.unwrap().filename.as_deref(), Some("benchy.gcode"));
+
+        let body = json!({"result": {"status": {"print_stats": {"state": "standby", "filename": ""}}}});
+        let connector = job_host(ConnectorKind::Moonraker, "/printer/objects/query", body).await;
+        assert_eq!(job(&connector).await.expect("job is read").filename, None);
+    }
+
+    #[actix_web::test]
+    async fn rejects_unknown_states() {
+        assert!(matches!(job_in("startup", None).await, Err(ConnectorError::Unexpected(_))));
+    }
+
+    #[actix_web::test]
+    async fn start_print_uploads_to_gcodes_and_prints() {
+        let (connector, upload) = upload_host(ConnectorKind::Moonraker, "/server/files/upload", 201).await;
+        connector.start_print("benchy.gcode", b"G28\n".to_vec()).await.expect("print starts");
+
+        let upload = upload.lock().unwrap();
+        assert_eq!(upload.api_key.as_deref(), Some(API_KEY));
+        assert!(upload.body.contains("filename=\"benchy.gcode\""));
+        assert!(has_field(&upload.body, "root", "gcodes"));
+        assert!(has_field(&upload.body, "print", "true"));
+    }
+}
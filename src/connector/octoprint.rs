@@ -0,0 +1,149 @@
+use super::{file_part, Connector, ConnectorError, RemoteJob, RemoteState};
+use reqwest::Method;
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct JobResponse {
+    state: String,
+    job: Option<JobInfo>,
+    progress: Option<Progress>,
+}
+
+#[derive(Deserialize)]
+struct JobInfo {
+    file: Option<JobFile>,
+}
+
+#[derive(Deserialize)]
+struct JobFile {
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Progress {
+    completion: Option<f32>,
+}
+
+/// `POST /api/files/local` with `print=true` uploads and selects the file and starts the job.
+pub async fn start_print(
+    connector: &Connector,
+    filename: &str,
+    content: Vec<u8>,
+) -> Result<(), ConnectorError> {
+    let form = reqwest::multipart::Form::new()
+        .part("file", file_part(filename, content)?)
+        .text("select", "true")
+        .text("print", "true");
+    connector
+        .request(Method::POST, "/api/files/local")
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// OctoPrint has no finished state, it returns to `Operational` after a job. Full completion
+/// tells a finished job from a cancelled one. The completion of the previous job stays until
+/// the next one starts, which the poller accounts for.
+pub async fn job(connector: &Connector) -> Result<RemoteJob, ConnectorError> {
+    let response: JobResponse = connector
+        .request(Method::GET, "/api/job")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    let progress = response.progress.and_then(|progress| progress.completion);
+    let filename = response.job.and_then(|job| job.file).and_then(|file| file.name);
+
+    let state = match response.state.as_str() {
+        "Printing" | "Pausing" | "Paused" | "Resuming" | "Finishing" | "Starting" => RemoteState::Printing,
+        "Cancelling" => RemoteState::Cancelled,
+        "Operational" => match progress {
+            Some(completion) if completion >= 100.0 => RemoteState::Done,
+            Some(_) => RemoteState::Cancelled,
+            None => RemoteState::Idle,
+        },
+        state if state.starts_with("Error") || state.starts_with("Offline after error") => RemoteState::Failed,
+        state => return Err(ConnectorError::Unexpected(format!("job state {}", state))),
+    };
+    Ok(RemoteJob { state, progress, filename })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connector::mock_host::{has_field, job_host, upload_host, API_KEY};
+    use super::*;
+    use crate::model::ConnectorKind;
+    use serde_json::json;
+
+    async fn job_in(state: &str, completion: Option<f32>) -> RemoteJob {
+        let body = json!({
+            "state": state,
+            "job": {"file": {"name": "benchy.gcode"}},
+            "progress": {"completion": completion}
+        });
+        let connector = job_host(ConnectorKind::OctoPrint, "/api/job", body).await;
+        job(&connector).await.expect("job is read")
+    }
+
+    #[actix_web::test]
+    async fn maps_running_states_to_printing() {
+        for state in ["Printing", "Pausing", "Paused", "Resuming", "Finishing", "Starting"] {
+            let remote = job_in(state, Some(42.5)).await;
+            assert_eq!(remote.state, RemoteState::Printing, "{}", state);
+            assert_eq!(remote.progress, Some(42.5));
+        }
+    }
+
+    #[actix_web::test]
+    async fn tells_finished_from_cancelled_by_completion() {
+        assert_eq!(job_in("Operational", Some(100.0)).await.state, RemoteState::Done);
+        assert_eq!(job_in("Operational", Some(63.0)).await.state, RemoteState::Cancelled);
+        assert_eq!(job_in("Operational", None).await.state, RemoteState::Idle);
+        assert_eq!(job_in("Cancelling", Some(63.0)).await.state, RemoteState::Cancelled);
+    }
+
+    #[actix_web::test]
+    async fn reads_the_selected_file() {
+        assert_eq!(job_in("Operational", Some(100.0)).await.filename.as_deref(), Some("benchy.gcode"));
+
+        let body = json!({"state": "Operational", "job": {"file": {"name": null}}, "progress": {"completion": null}});
+        let connector = job_host(ConnectorKind::OctoPrint, "/api/job", body).await;
+        assert_eq!(job(&connector).await.expect("job is read").filename, None);
+    }
+
+    #[actix_web::test]
+    async fn maps_errors_to_failed() {
+        assert_eq!(job_in("Error: Heater failure", Some(10.0)).await.state, RemoteState::Failed);
+        assert_eq!(job_in("Offline after error", None).await.state, RemoteState::Failed);
+    }
+
+    #[actix_web::test]
+    async fn rejects_unknown_states() {
+        let body = json!({"state": "Transfering file to SD", "progress": {"completion": null}});
+        let connector = job_host(ConnectorKind::OctoPrint, "/api/job", body).await;
+        assert!(matches!(job(&connector).await, Err(ConnectorError::Unexpected(_))));
+    }
+
+    #[actix_web::test]
+    async fn start_print_uploads_selects_and_prints() {
+        let (connector, upload) = upload_host(ConnectorKind::OctoPrint, "/api/files/local", 201).await;
+        connector.start_print("benchy", b"G28\n".to_vec()).await.expect("print starts");
+
+        let upload = upload.lock().unwrap();
+        assert_eq!(upload.api_key.as_deref(), Some(API_KEY));
+        assert!(upload.body.contains("filename=\"benchy.gcode\""));
+        assert!(upload.body.contains("G28"));
+        assert!(has_field(&upload.body, "select", "true"));
+        assert!(has_field(&upload.body, "print", "true"));
+    }
+
+    #[actix_web::test]
+    async fn start_print_fails_if_the_host_refuses() {
+        let (connector, _) = upload_host(ConnectorKind::OctoPrint, "/api/files/local", 409).await;
+        let result = connector.start_print("benchy.gcode", b"G28\n".to_vec()).await;
+        assert!(matches!(result, Err(ConnectorError::Http(_))));
+    }
+}
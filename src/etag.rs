@@ -0,0 +1,39 @@
+use actix_web::http::header::{EntityTag, IfMatch, IfNoneMatch};
+use actix_web::{HttpMessage, HttpRequest};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+pub fn file_etag(version: i32) -> EntityTag {
+    EntityTag::new_strong(version.to_string())
+}
+
+/// A listing changes whenever one of its files changes or the selection itself changes.
+pub fn listing_etag(files: impl Iterator<Item = (Uuid, i32)>) -> EntityTag {
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.hash(&mut hasher);
+    }
+    EntityTag::new_weak(format!("{:x}", hasher.finish()))
+}
+
+/// Versions accepted by the `If-Match` header, `None` if the header is missing or `*`.
+pub fn if_match_versions(req: &HttpRequest) -> Option<Vec<i32>> {
+    match req.get_header::<IfMatch>()? {
+        IfMatch::Any => None,
+        IfMatch::Items(tags) => Some(
+            tags.iter()
+                .filter(|tag| !tag.weak)
+                .filter_map(|tag| tag.tag().parse().ok())
+                .collect(),
+        ),
+    }
+}
+
+pub fn is_not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
@@ -1,36 +1,39 @@
 use crate::{
-    model::FileResponseModel,
-    schema::{CreateFile, UpdateFile, FilterOptions},
+    auth::AuthUser,
+    bundle::{read_zip, zip_response_then, ImportEntry},
+    etag::{file_etag, if_match_versions, is_not_modified, listing_etag},
+    gcode_preview::filament_length,
+    model::{DownloadableFile, FileResponseModel, ImportItemResult},
+    schema::{BulkFileIds, BulkUpdateFiles, CreateFile, ImportOptions, UpdateFile, FilterOptions},
     AppState,
 };
 
-use actix_web::{delete, get, patch, post, web, HttpResponse, Responder};
+use actix_web::{delete, get, http::header, patch, post, web, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
 use uuid::Uuid;
 use crate::query_service::file_queries::*;
+use crate::query_service::gcode_queries::update_filament_length;
 
 #[utoipa::path(
 context_path = "/api",
 responses(
 (status = 200, description = "OK", body = Vec<FileResponse>),
+(status = 304, description = "Not modified since the ETag in If-None-Match"),
+(status = 401, description = "Not authenticated", body = String),
 (status = 404, description = "Files not found", body = String),
 (status = 500, description = "Internal server error", body = String)
-),
-params(
-("userid" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
 ))]
-#[get("/files/private/{userid}")]
+#[get("/files/private")]
 pub async fn get_private_files(
+    req: HttpRequest,
+    user: AuthUser,
     opts: web::Query<FilterOptions>,
     data: web::Data<AppState>,
-    path: web::Path<String>,
 ) -> impl Responder {
-    let id = path.into_inner();
-    let id = Uuid::parse_str(&id).unwrap_or(Uuid::new_v4());
     let limit = opts.limit.unwrap_or(10);
     let offset = (opts.page.unwrap_or(1) - 1) * limit;
 
-    let query_result = select_private(id, limit, offset, data)
+    let query_result = select_private(user.id, limit, offset, data)
         .await;
     if query_result.is_err() {
         let message = "Something bad happened while fetching all file items";
@@ -39,18 +42,24 @@ pub async fn get_private_files(
     }
 
     let files = query_result.unwrap();
-    HttpResponse::Ok().json(files)
+    let etag = listing_etag(files.iter().map(|file| (file.id, file.version)));
+    if is_not_modified(&req, &etag) {
+        return HttpResponse::NotModified().insert_header(header::ETag(etag)).finish();
+    }
+    HttpResponse::Ok().insert_header(header::ETag(etag)).json(files)
 }
 
 #[utoipa::path(
 context_path = "/api",
 responses(
 (status = 200, description = "OK", body = Vec<FileResponse>),
+(status = 304, description = "Not modified since the ETag in If-None-Match"),
 (status = 404, description = "Files not found", body = String),
 (status = 500, description = "Internal server error", body = String)
 ))]
 #[get("/files/public")]
 pub async fn get_public_files(
+    req: HttpRequest,
     opts: web::Query<FilterOptions>,
     data: web::Data<AppState>,
 ) -> impl Responder {
@@ -66,7 +75,11 @@ pub async fn get_public_files(
     }
 
     let files = query_result.unwrap();
-    HttpResponse::Ok().json(files)
+    let etag = listing_etag(files.iter().map(|file| (file.id, file.version)));
+    if is_not_modified(&req, &etag) {
+        return HttpResponse::NotModified().insert_header(header::ETag(etag)).finish();
+    }
+    HttpResponse::Ok().insert_header(header::ETag(etag)).json(files)
 }
 
 #[utoipa::path(
@@ -74,16 +87,18 @@ context_path = "/api",
 responses(
 (status = 201, description = "Created", body = FileResponse),
 (status = 400, description = "Bad request", body = String),
+(status = 401, description = "Not authenticated", body = String),
 (status = 500, description = "Internal server error", body = String)
 ),
-request_body(content = CreateFile, description="all parameters are required"),
+request_body(content = CreateFile, description="all parameters are required, the caller becomes the owner"),
 )]
 #[post("/files")]
 pub async fn create_file(
+    user: AuthUser,
     body: web::Json<CreateFile>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let query_result = insert_file(body, data).await;
+    let query_result = insert_file(body, user.id, data).await;
     let result = match query_result {
         Ok(file) => HttpResponse::Created().json(file),
         Err(e) => {
@@ -103,8 +118,9 @@ pub async fn create_file(
 #[utoipa::path(
 context_path = "/api",
 responses(
-(status = 200, description = "OK", body = Vec<FileResponse>),
-(status = 404, description = "Files not found", body = String),
+(status = 200, description = "OK", body = FileResponse),
+(status = 304, description = "Not modified since the ETag in If-None-Match"),
+(status = 404, description = "File not found, hidden or not shared with the caller", body = String),
 (status = 500, description = "Internal server error", body = String)
 ),
 params(
@@ -112,24 +128,39 @@ params(
 ))]
 #[get("/files/all/{id}")]
 pub async fn get_file(
+    req: HttpRequest,
+    user: Option<AuthUser>,
     path: web::Path<Uuid>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let file_id = path.into_inner();
     let query_result = sqlx::query_as!(
         FileResponseModel,
-        "select * from file where id = $1",
-        file_id
+        "select id, fullname, created, sizebytes, downloads, average_rating, is_downloadable, is_public,
+            description, license, version
+        from file where id = $1 and ((is_public and not is_hidden)
+            or exists(select 1 from effective_file_role fpu
+                where fpu.files_pk = file.id and fpu.user_account_pk = $2))",
+        file_id,
+        user.map(|user| user.id)
     )
-    .fetch_one(&data.db)
+    .fetch_optional(&data.db)
     .await;
 
     return match query_result {
-        Ok(file) => HttpResponse::Ok().json(file),
-        Err(_) => {
+        Ok(Some(file)) => {
+            let etag = file_etag(file.version);
+            if is_not_modified(&req, &etag) {
+                return HttpResponse::NotModified().insert_header(header::ETag(etag)).finish();
+            }
+            HttpResponse::Ok().insert_header(header::ETag(etag)).json(file)
+        }
+        Ok(None) => {
             let message = format!("File with ID:{} not found", file_id);
             HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
         }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
     };
 }
 
@@ -137,58 +168,47 @@ pub async fn get_file(
 #[utoipa::path(
 context_path = "/api",
 responses(
-(status = 200, description = "OK", body = Vec<FileResponse>),
-(status = 404, description = "Files not found", body = String),
+(status = 200, description = "OK", body = FileResponse),
+(status = 400, description = "Empty name or server maintained field supplied", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of file", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 412, description = "File was changed since the ETag in If-Match", body = String),
 (status = 500, description = "Internal server error", body = String)
 ),
-request_body(content = UpdateFile, description="not all parameters are required"),
+request_body(content = UpdateFile, description="only supplied parameters are changed"),
 params(
 ("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
 ))]
 #[patch("/files/{id}")]
 pub async fn edit_file(
-    path: web::Path<String>,
+    req: HttpRequest,
+    user: AuthUser,
+    path: web::Path<Uuid>,
     body: web::Json<UpdateFile>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let file_id = path.into_inner();
-    let id = Uuid::parse_str(&file_id).unwrap_or(Uuid::new_v4());
-    let query_result = sqlx::query_as!(
-        FileResponseModel,
-        "SELECT * FROM file WHERE id = $1",
-        id
-    )
-    .fetch_one(&data.db)
-    .await;
-
-    if query_result.is_err() {
-        let message = format!("File with ID: {} not found", file_id);
-        return HttpResponse::NotFound()
-            .json(serde_json::json!({"status": "fail","message": message}));
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    if body.fullname.as_deref().is_some_and(|name| name.trim().is_empty()) {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "fullname must not be empty"}));
     }
 
-    let note = query_result.unwrap();
-
-    let query_result = sqlx::query_as!(
-        FileResponseModel,
-        "UPDATE file SET fullname = $1, downloads = $2, average_rating = $3 WHERE id = $4 RETURNING *",
-        body.fullname.to_owned().unwrap_or(note.fullname),
-        body.downloads.to_owned().unwrap_or(note.downloads.unwrap()),
-        body.average_rating.unwrap_or(note.average_rating.unwrap()),
-        id
-    )
-    .fetch_one(&data.db)
-    .await
-    ;
+    let query_result = update_file(file_id, &body, if_match_versions(&req), &data).await;
 
     return match query_result {
-        Ok(note) => {
+        Ok(Some(note)) => {
+            let etag = file_etag(note.version);
             let note_response = json!({"status": "success","data": serde_json::json!({
                 "note": note
             })});
 
-            HttpResponse::Ok().json(note_response)
+            HttpResponse::Ok().insert_header(header::ETag(etag)).json(note_response)
         }
+        Ok(None) => missing_or_changed(file_id, &data).await,
         Err(err) => {
             let message = format!("Error: {:?}", err);
             HttpResponse::InternalServerError().json(json!({"status": "error","message": message}))
@@ -199,8 +219,11 @@ pub async fn edit_file(
 #[utoipa::path(
 context_path = "/api",
 responses(
-(status = 200, description = "OK"),
-(status = 204, description = "File not found", body = String),
+(status = 204, description = "File deleted with its G-code, prints and print jobs"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of file", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 412, description = "File was changed since the ETag in If-Match", body = String),
 (status = 500, description = "Internal server error", body = String)
 ),
 params(
@@ -208,20 +231,259 @@ params(
 ))]
 #[delete("/files/{id}")]
 pub async fn delete_file(
+    req: HttpRequest,
+    user: AuthUser,
     path: web::Path<Uuid>,
     data: web::Data<AppState>
 ) -> impl Responder {
     let file_id = path.into_inner();
-    let rows_affected = sqlx::query!("DELETE FROM file WHERE id = $1", file_id)
-        .execute(&data.db)
-        .await
-        .unwrap()
-        .rows_affected();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    match delete_file_versioned(file_id, if_match_versions(&req), &data).await {
+        Ok(Some(blobs)) => {
+            for blob in blobs {
+                let _ = data.storage.delete(blob).await;
+            }
+            HttpResponse::NoContent().finish()
+        }
+        Ok(None) => missing_or_changed(file_id, &data).await,
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+/// Tells apart why a conditional write touched no row.
+async fn missing_or_changed(file_id: Uuid, data: &web::Data<AppState>) -> HttpResponse {
+    match file_exists(file_id, data).await {
+        Ok(true) => HttpResponse::PreconditionFailed().json(
+            json!({"status": "fail","message": "File was modified, reload it and try again"}),
+        ),
+        Ok(false) => {
+            let message = format!("File with ID: {} not found", file_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, result per file", body = Vec<BulkItemResult>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = BulkUpdateFiles, description="ids are required, all other parameters are optional"),
+)]
+#[patch("/files/bulk")]
+pub async fn bulk_edit_files(
+    user: AuthUser,
+    body: web::Json<BulkUpdateFiles>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match bulk_update(user.id, &body, data).await {
+        Ok(results) => HttpResponse::Ok().json(results),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, result per file", body = Vec<BulkItemResult>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = BulkFileIds),
+)]
+#[post("/files/bulk/delete")]
+pub async fn bulk_delete_files(
+    user: AuthUser,
+    body: web::Json<BulkFileIds>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match bulk_delete(user.id, &body.ids, data.clone()).await {
+        Ok((results, blobs)) => {
+            for blob in blobs {
+                let _ = data.storage.delete(blob).await;
+            }
+            HttpResponse::Ok().json(results)
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
 
-    if rows_affected == 0 {
-        let message = format!("File with ID: {} not found", file_id);
-        return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+/// Streams the files as a ZIP archive and counts a download for each file that was read
+/// into it, so files missing from storage are not counted.
+pub fn download_response(
+    files: Vec<DownloadableFile>,
+    filename: &str,
+    data: &web::Data<AppState>,
+) -> HttpResponse {
+    let data = data.clone();
+    zip_response_then(
+        data.storage.clone(),
+        files.into_iter().map(Into::into).collect(),
+        filename,
+        move |read| async move {
+            if let Err(e) = count_downloads(&read, &data).await {
+                println!("🔥 Counting downloads failed: {:?}", e);
+            }
+        },
+    )
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "ZIP archive of all requested files the caller may download", content_type = "application/zip"),
+(status = 404, description = "None of the files can be downloaded", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = BulkFileIds),
+)]
+#[post("/files/download")]
+pub async fn download_files(
+    user: Option<AuthUser>,
+    body: web::Json<BulkFileIds>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let query_result = select_downloadable_files(&body.ids, user.map(|user| user.id), &data).await;
+    match query_result {
+        Ok(files) if files.is_empty() => HttpResponse::NotFound()
+            .json(json!({"status": "fail","message": "No downloadable files found"})),
+        Ok(files) => download_response(files, "files.zip", &data),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
     }
+}
 
-    HttpResponse::NoContent().finish()
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, result per archive entry", body = Vec<ImportItemResult>),
+(status = 400, description = "Body is not a ZIP archive or has more than 1000 entries", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = Vec<u8>, description = "ZIP archive of models and G-code", content_type = "application/zip"),
+params(
+("isPublic" = Option<bool>, Query, description = "Visibility of all imported files, defaults to true"),
+("isDownloadable" = Option<bool>, Query, description = "Downloadability of all imported files, defaults to true"),
+("tags" = Option<String>, Query, description = "Comma separated tags applied to all imported files")
+))]
+#[post("/files/import")]
+pub async fn import_files(
+    user: AuthUser,
+    opts: web::Query<ImportOptions>,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let entries = match web::block(move || read_zip(body)).await {
+        Ok(Ok(entries)) => entries,
+        Ok(Err(e)) => {
+            let message = format!("Not a valid ZIP archive: {}", e);
+            return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+
+    let tags = normalize_tags(
+        &opts.tags.as_ref().map(|tags| tags.split(',').map(str::to_string).collect()),
+    );
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        results.push(import_entry(user, &opts, &tags, entry, &data).await);
+    }
+    HttpResponse::Ok().json(results)
+}
+
+/// Imports one archive entry in its own transaction, so a failing entry leaves the others intact.
+async fn import_entry(
+    user: AuthUser,
+    opts: &ImportOptions,
+    tags: &[String],
+    entry: ImportEntry,
+    data: &web::Data<AppState>,
+) -> ImportItemResult {
+    let is_gcode = entry.is_gcode();
+    let name = entry.name;
+    let fail = |status: &str, message: String| ImportItemResult {
+        name: name.clone(),
+        status: status.to_string(),
+        id: None,
+        gcode_id: None,
+        message: Some(message),
+    };
+    let content = match entry.content {
+        Ok(content) => content,
+        Err(message) => return fail("fail", message),
+    };
+
+    let result = async {
+        let mut tx = data.db.begin().await.map_err(|e| format!("{:?}", e))?;
+        let file = insert_imported_file(
+            &mut tx, &name, content.len() as i64, user.id, opts, tags, is_gcode,
+        )
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+        if let Some(gcode_id) = file.gcode_id {
+            update_filament_length(&mut tx, gcode_id, filament_length(&content))
+                .await
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        data.storage.write(file.id, &content).await.map_err(|e| e.to_string())?;
+        if let Err(e) = tx.commit().await {
+            let _ = data.storage.delete(file.id).await;
+            return Err(format!("{:?}", e));
+        }
+        Ok(file)
+    }
+        .await;
+
+    match result {
+        Ok(file) => ImportItemResult {
+            name: name.clone(),
+            status: "success".to_string(),
+            id: Some(file.id),
+            gcode_id: file.gcode_id,
+            message: None,
+        },
+        Err(message) => fail("error", message),
+    }
+}
+
+pub fn file_not_found(file_id: Uuid) -> HttpResponse {
+    let message = format!("File with ID: {} not found", file_id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+/// Lets owners of the file pass, whether their role is granted directly, through a group or
+/// through the organization owning the file.
+pub async fn require_file_owner(
+    file_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>,
+) -> Result<(), HttpResponse> {
+    let internal_error = |e: sqlx::Error| {
+        HttpResponse::InternalServerError().json(json!({"status": "error","message": format!("{:?}", e)}))
+    };
+    match file_exists(file_id, data).await {
+        Ok(true) => {}
+        Ok(false) => return Err(file_not_found(file_id)),
+        Err(e) => return Err(internal_error(e)),
+    }
+    match select_file_role(file_id, user_id, data).await {
+        Ok(Some(role)) if role == "owner" => Ok(()),
+        Ok(_) => Err(HttpResponse::Forbidden()
+            .json(json!({"status": "fail","message": "Not owner of file"}))),
+        Err(e) => Err(internal_error(e)),
+    }
 }
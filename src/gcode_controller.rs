@@ -0,0 +1,268 @@
+use crate::{
+    auth::AuthUser,
+    gcode_preview::{filament_length, parse_layers},
+    model::FilamentUsage,
+    schema::{FilamentUsageOptions, LayerRangeOptions, LinkGcodeVariant},
+    AppState,
+};
+
+use actix_web::{delete, get, post, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::gcode_queries::*;
+
+/// Upper bound on the layers returned by one preview request.
+const MAX_PREVIEW_LAYERS: usize = 100;
+
+fn variant_error(e: sqlx::Error) -> HttpResponse {
+    let message = e.to_string();
+    if message.contains("violates foreign key constraint") {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Referenced printer or material does not exist"}));
+    }
+    if message.contains("violates check constraint") {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "A G-code cannot be a variant of its own file"}));
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn model_not_found(id: Uuid) -> HttpResponse {
+    let message = format!("File with ID: {} not found", id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, toolpaths of the requested layers", body = GcodeLayers),
+(status = 400, description = "Invalid layer range", body = String),
+(status = 404, description = "Gcode not found or not downloadable by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Gcode Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("from" = Option<usize>, Query, description = "First layer index, defaults to 0"),
+("to" = Option<usize>, Query, description = "Last layer index (inclusive), at most 100 layers per request")
+))]
+#[get("/gcode/{id}/layers")]
+pub async fn get_gcode_layers(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    opts: web::Query<LayerRangeOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let gcode_id = path.into_inner();
+    let from = opts.from.unwrap_or(0);
+    let to = opts.to.unwrap_or(from.saturating_add(MAX_PREVIEW_LAYERS - 1));
+    if to < from || to - from >= MAX_PREVIEW_LAYERS {
+        let message = format!("to must be between from and from + {}", MAX_PREVIEW_LAYERS - 1);
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+
+    let file = match select_downloadable_gcode_file(gcode_id, user.map(|user| user.id), &data).await {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            let message = format!("Gcode with ID: {} not found", gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+    let content = match data.storage.read(file.id).await {
+        Ok(content) => content,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+
+    match web::block(move || parse_layers(&content, from, to)).await {
+        Ok(layers) => HttpResponse::Ok().json(layers),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, grams and cost are null without a material or its properties", body = FilamentUsage),
+(status = 400, description = "Unknown material", body = String),
+(status = 404, description = "Gcode not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Gcode Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("materialId" = Option<String>, Query, description = "Material used to derive grams and cost")
+))]
+#[get("/gcode/{id}/filament-usage")]
+pub async fn get_filament_usage(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    opts: web::Query<FilamentUsageOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let gcode_id = path.into_inner();
+    let gcode = match select_gcode_filament(gcode_id, user.map(|user| user.id), &data).await {
+        Ok(Some(gcode)) => gcode,
+        Ok(None) => {
+            let message = format!("Gcode with ID: {} not found", gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+
+    let length_mm = match gcode.filament_length_mm {
+        Some(length_mm) => Some(length_mm),
+        None => match measure_filament(gcode_id, gcode.file_id, &data).await {
+            Ok(length_mm) => length_mm,
+            Err(e) => {
+                return HttpResponse::InternalServerError()
+                    .json(json!({"status": "error","message": format!("{:?}", e)}));
+            }
+        },
+    };
+
+    let material_id = match opts.material_id {
+        Some(material_id) => material_id,
+        None => return HttpResponse::Ok().json(FilamentUsage { length_mm, grams: None, cost: None }),
+    };
+    match select_material_usage(material_id, length_mm, &data).await {
+        Ok(Some(usage)) => HttpResponse::Ok().json(usage),
+        Ok(None) => {
+            let message = format!("Material with ID: {} not found", material_id);
+            HttpResponse::BadRequest().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+/// G-code stored before the length was extracted on upload is measured on first use.
+/// Returns `None` if the file content is not in storage.
+async fn measure_filament(
+    gcode_id: Uuid,
+    file_id: Uuid,
+    data: &web::Data<AppState>,
+) -> Result<Option<f64>, sqlx::Error> {
+    let content = match data.storage.read(file_id).await {
+        Ok(content) => content,
+        Err(_) => return Ok(None),
+    };
+    let length_mm = match web::block(move || filament_length(&content)).await {
+        Ok(length_mm) => length_mm,
+        Err(_) => return Ok(None),
+    };
+    let mut conn = data.db.acquire().await?;
+    update_filament_length(&mut conn, gcode_id, length_mm).await?;
+    Ok(Some(length_mm))
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the most successful variants first", body = Vec<GcodeVariantModel>),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Model file Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/files/{id}/gcode-variants")]
+pub async fn get_gcode_variants(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    let user_id = user.map(|user| user.id);
+    match is_file_visible(file_id, user_id, &data).await {
+        Ok(true) => {}
+        Ok(false) => return model_not_found(file_id),
+        Err(e) => return variant_error(e),
+    }
+    match select_gcode_variants(file_id, None, user_id, &data).await {
+        Ok(variants) => HttpResponse::Ok().json(variants),
+        Err(e) => variant_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the linked variant", body = GcodeVariantModel),
+(status = 400, description = "Unknown printer or material, or G-code of the model file itself", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "File not found, or G-code not found or not owned by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = LinkGcodeVariant, description="gcodeId is required, a G-code already linked to another model is moved"),
+params(
+("id" = String, Path, description = "Model file Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/files/{id}/gcode-variants")]
+pub async fn link_gcode_to_model(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<LinkGcodeVariant>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    match is_file_visible(file_id, Some(user.id), &data).await {
+        Ok(true) => {}
+        Ok(false) => return model_not_found(file_id),
+        Err(e) => return variant_error(e),
+    }
+    match link_gcode_variant(file_id, &body, user.id, &data).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let message = format!("Gcode with ID: {} not found", body.gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => return variant_error(e),
+    }
+    match select_gcode_variants(file_id, Some(body.gcode_id), Some(user.id), &data).await {
+        Ok(variants) => match variants.into_iter().next() {
+            Some(variant) => HttpResponse::Ok().json(variant),
+            None => HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": "Linked variant could not be read"})),
+        },
+        Err(e) => variant_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Unlinked, the G-code file itself is kept"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Variant not found or G-code not owned by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Model file Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("gcode_id" = String, Path, description = "Gcode Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/files/{id}/gcode-variants/{gcode_id}")]
+pub async fn unlink_gcode_from_model(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (file_id, gcode_id) = path.into_inner();
+    match unlink_gcode_variant(file_id, gcode_id, user.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("Gcode with ID: {} is not a variant of file {}", gcode_id, file_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => variant_error(e),
+    }
+}
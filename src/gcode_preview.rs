@@ -0,0 +1,358 @@
+use crate::model::{GcodeLayer, GcodeLayers};
+
+/// Z heights closer than this belong to the same layer.
+const Z_EPSILON: f32 = 1e-4;
+const MM_PER_INCH: f32 = 25.4;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Extrusion,
+    Travel,
+}
+
+/// Splits G-code into layers and keeps the toolpaths of the layers in `from..=to`.
+///
+/// A layer starts with the first extruding move at a new Z height, so Z hops and travel
+/// moves do not create layers of their own. Travels are attributed to the layer of the
+/// next extrusion. Arcs (`G2`/`G3`) are drawn as straight lines to their end point.
+pub fn parse_layers(content: &[u8], from: usize, to: usize) -> GcodeLayers {
+    let mut parser = Parser::new(from, to);
+    for line in content.split(|byte| *byte == b'\n') {
+        parser.line(&String::from_utf8_lossy(line));
+    }
+    parser.finish()
+}
+
+/// Net length of filament pushed into the extruder in millimetres. Retractions count
+/// negatively, so a retract followed by the matching unretract adds nothing.
+pub fn filament_length(content: &[u8]) -> f64 {
+    // an empty layer range keeps no toolpaths
+    let mut parser = Parser::new(1, 0);
+    for line in content.split(|byte| *byte == b'\n') {
+        parser.line(&String::from_utf8_lossy(line));
+    }
+    parser.extruded
+}
+
+struct Parser {
+    from: usize,
+    to: usize,
+    x: f32,
+    y: f32,
+    z: f32,
+    e: f32,
+    absolute: bool,
+    absolute_e: bool,
+    scale: f32,
+    extruded: f64,
+    layer_count: usize,
+    /// Z of the most recently started layer, which may be outside the requested range.
+    layer_z: f32,
+    layers: Vec<GcodeLayer>,
+    /// Travels seen since the last extrusion, in the layer they were made on.
+    pending_travels: Vec<Vec<f32>>,
+    open: Option<Segment>,
+}
+
+impl Parser {
+    fn new(from: usize, to: usize) -> Self {
+        Parser {
+            from,
+            to,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            e: 0.0,
+            absolute: true,
+            absolute_e: true,
+            scale: 1.0,
+            extruded: 0.0,
+            layer_count: 0,
+            layer_z: 0.0,
+            layers: Vec::new(),
+            pending_travels: Vec::new(),
+            open: None,
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        let code = strip_comments(line);
+        let words = words(&code);
+        let command = match words.iter().find(|(letter, _)| *letter == 'G' || *letter == 'M') {
+            Some((letter, number)) => (*letter, *number as i32),
+            None => return,
+        };
+        let value = |axis: char| {
+            words
+                .iter()
+                .find(|(letter, _)| *letter == axis)
+                .map(|(_, value)| *value)
+        };
+
+        match command {
+            ('G', 0..=3) => self.movement(value('X'), value('Y'), value('Z'), value('E')),
+            ('G', 20) => self.scale = MM_PER_INCH,
+            ('G', 21) => self.scale = 1.0,
+            ('G', 28) => {
+                let all = value('X').is_none() && value('Y').is_none() && value('Z').is_none();
+                if all || value('X').is_some() {
+                    self.x = 0.0;
+                }
+                if all || value('Y').is_some() {
+                    self.y = 0.0;
+                }
+                if all || value('Z').is_some() {
+                    self.z = 0.0;
+                }
+                self.open = None;
+            }
+            ('G', 90) => {
+                self.absolute = true;
+                self.absolute_e = true;
+            }
+            ('G', 91) => {
+                self.absolute = false;
+                self.absolute_e = false;
+            }
+            ('G', 92) => {
+                if let Some(x) = value('X') {
+                    self.x = x * self.scale;
+                }
+                if let Some(y) = value('Y') {
+                    self.y = y * self.scale;
+                }
+                if let Some(z) = value('Z') {
+                    self.z = z * self.scale;
+                }
+                if let Some(e) = value('E') {
+                    self.e = e * self.scale;
+                }
+                self.open = None;
+            }
+            ('M', 82) => self.absolute_e = true,
+            ('M', 83) => self.absolute_e = false,
+            _ => {}
+        }
+    }
+
+    fn movement(&mut self, x: Option<f32>, y: Option<f32>, z: Option<f32>, e: Option<f32>) {
+        let target = |current: f32, value: Option<f32>, absolute: bool, scale: f32| match value {
+            Some(value) if absolute => value * scale,
+            Some(value) => current + value * scale,
+            None => current,
+        };
+        let x = target(self.x, x, self.absolute, self.scale);
+        let y = target(self.y, y, self.absolute, self.scale);
+        let z = target(self.z, z, self.absolute, self.scale);
+        let e = target(self.e, e, self.absolute_e, self.scale);
+
+        self.extruded += f64::from(e - self.e);
+        let moved = x != self.x || y != self.y;
+        let extruding = e > self.e && moved;
+        let (start_x, start_y) = (self.x, self.y);
+        if z != self.z {
+            self.open = None;
+        }
+        self.x = x;
+        self.y = y;
+        self.z = z;
+        self.e = e;
+        if !moved {
+            return;
+        }
+
+        if extruding {
+            self.extrusion(start_x, start_y);
+        } else {
+            self.travel(start_x, start_y);
+        }
+    }
+
+    fn extrusion(&mut self, start_x: f32, start_y: f32) {
+        if self.layer_count == 0 || (self.z - self.layer_z).abs() > Z_EPSILON {
+            self.layer_count += 1;
+            self.layer_z = self.z;
+            self.open = None;
+            if self.in_range() {
+                self.layers.push(GcodeLayer {
+                    index: self.layer_count - 1,
+                    z: round(self.z),
+                    extrusions: Vec::new(),
+                    travels: Vec::new(),
+                });
+            }
+        }
+
+        let pending = std::mem::take(&mut self.pending_travels);
+        if !self.in_range() {
+            self.open = None;
+            return;
+        }
+        let layer = self.layers.last_mut().expect("layer in range was pushed");
+        layer.travels.extend(pending);
+        if self.open != Some(Segment::Extrusion) {
+            layer.extrusions.push(vec![round(start_x), round(start_y)]);
+            self.open = Some(Segment::Extrusion);
+        }
+        let polyline = layer.extrusions.last_mut().expect("polyline was pushed");
+        polyline.extend([round(self.x), round(self.y)]);
+    }
+
+    fn travel(&mut self, start_x: f32, start_y: f32) {
+        if self.open != Some(Segment::Travel) {
+            self.pending_travels.push(vec![round(start_x), round(start_y)]);
+            self.open = Some(Segment::Travel);
+        }
+        let polyline = self.pending_travels.last_mut().expect("polyline was pushed");
+        polyline.extend([round(self.x), round(self.y)]);
+    }
+
+    fn in_range(&self) -> bool {
+        self.layer_count > 0 && (self.from..=self.to).contains(&(self.layer_count - 1))
+    }
+
+    fn finish(mut self) -> GcodeLayers {
+        let pending = std::mem::take(&mut self.pending_travels);
+        if self.in_range() {
+            if let Some(layer) = self.layers.last_mut() {
+                layer.travels.extend(pending);
+            }
+        }
+        GcodeLayers {
+            layer_count: self.layer_count,
+            layers: self.layers,
+        }
+    }
+}
+
+/// Drops `;` comments, `(...)` comments and the `*` checksum.
+fn strip_comments(line: &str) -> String {
+    let line = line.split(';').next().unwrap_or_default();
+    let line = line.split('*').next().unwrap_or_default();
+    let mut code = String::with_capacity(line.len());
+    let mut depth = 0;
+    for c in line.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            _ if depth == 0 => code.push(c.to_ascii_uppercase()),
+            _ => {}
+        }
+    }
+    code
+}
+
+/// Letter/number pairs of a line, with or without whitespace between them (`G1X10Y5`).
+fn words(code: &str) -> Vec<(char, f32)> {
+    let mut words = Vec::new();
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii_alphabetic() {
+            continue;
+        }
+        let mut number = String::new();
+        while let Some(&next) = chars.peek() {
+            if next.is_ascii_digit() || next == '.' || next == '-' || next == '+' {
+                number.push(next);
+                chars.next();
+            } else if next == ' ' && number.is_empty() {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if let Ok(value) = number.parse() {
+            words.push((c, value));
+        }
+    }
+    words
+}
+
+/// Micrometre precision keeps the JSON compact.
+fn round(value: f32) -> f32 {
+    (value * 1000.0).round() / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(gcode: &str, from: usize, to: usize) -> GcodeLayers {
+        parse_layers(gcode.as_bytes(), from, to)
+    }
+
+    #[test]
+    fn relative_extrusion_starts_layers_and_counts_filament() {
+        let gcode = "M83\n\
+            G1 Z0.2\n\
+            G1 X10 Y0 E1.5\n\
+            G1 X10 Y10 E1.5\n\
+            G1 E-0.5\n\
+            G1 Z0.4\n\
+            G1 E0.5\n\
+            G1 X0 Y10 E1\n";
+        let parsed = layers(gcode, 0, 10);
+        assert_eq!(parsed.layer_count, 2);
+        assert_eq!(parsed.layers[0].extrusions, vec![vec![0.0, 0.0, 10.0, 0.0, 10.0, 10.0]]);
+        assert_eq!(parsed.layers[1].z, 0.4);
+        assert_eq!(parsed.layers[1].extrusions, vec![vec![10.0, 10.0, 0.0, 10.0]]);
+        assert!((filament_length(gcode.as_bytes()) - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn g92_resets_extruder_without_losing_filament() {
+        let gcode = "G90\n\
+            G1 Z0.2\n\
+            G1 X10 E5\n\
+            G92 E0\n\
+            G1 X20 E2\n\
+            G1 Z0.4\n\
+            G92 E0\n\
+            G1 X30 E1\n";
+        let parsed = layers(gcode, 0, 10);
+        assert_eq!(parsed.layer_count, 2);
+        // the extrusion after the reset continues the layer instead of reading as a retraction
+        assert_eq!(parsed.layers[0].extrusions.len(), 2);
+        assert!(parsed.layers[0].travels.is_empty());
+        assert!((filament_length(gcode.as_bytes()) - 8.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn z_hop_does_not_start_a_layer() {
+        let gcode = "G1 Z0.2\n\
+            G1 X10 E1\n\
+            G1 Z0.6\n\
+            G1 X20 Y20\n\
+            G1 Z0.2\n\
+            G1 X30 Y20 E2\n\
+            G1 Z0.4\n\
+            G1 X40 Y20 E3\n";
+        let parsed = layers(gcode, 0, 10);
+        assert_eq!(parsed.layer_count, 2);
+        assert_eq!(parsed.layers[0].z, 0.2);
+        assert_eq!(parsed.layers[0].travels, vec![vec![10.0, 0.0, 20.0, 20.0]]);
+        assert_eq!(parsed.layers[1].z, 0.4);
+    }
+
+    #[test]
+    fn keeps_only_the_requested_layers() {
+        let gcode = "G1 Z0.2\n\
+            G1 X10 E1\n\
+            G1 Z0.4\n\
+            G0 X0 Y5\n\
+            G1 X10 E2\n\
+            G1 Z0.6\n\
+            G1 X0 E3\n";
+        let parsed = layers(gcode, 1, 1);
+        assert_eq!(parsed.layer_count, 3);
+        assert_eq!(parsed.layers.len(), 1);
+        assert_eq!(parsed.layers[0].index, 1);
+        assert_eq!(parsed.layers[0].z, 0.4);
+        assert_eq!(parsed.layers[0].travels, vec![vec![10.0, 0.0, 0.0, 5.0]]);
+        assert_eq!(parsed.layers[0].extrusions, vec![vec![0.0, 5.0, 10.0, 5.0]]);
+
+        let past_the_end = layers(gcode, 5, 9);
+        assert_eq!(past_the_end.layer_count, 3);
+        assert!(past_the_end.layers.is_empty());
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    auth::AuthUser,
+    files_controller::require_file_owner,
+    schema::{CreateGroup, GrantFileRole},
+    AppState, GetIdSchema,
+};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::group_queries::*;
+
+fn group_error(e: sqlx::Error) -> HttpResponse {
+    let message = e.to_string();
+    if message.contains("duplicate key") {
+        return HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "You already have a group with this name"}));
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn group_not_found(group_id: Uuid) -> HttpResponse {
+    let message = format!("Group with ID: {} not found", group_id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+/// Members may look at a group, only its owner may change it. Outsiders get a 404, so groups
+/// are not revealed to them.
+async fn require_group_access(
+    group_id: Uuid,
+    user_id: Uuid,
+    owner_only: bool,
+    data: &web::Data<AppState>,
+) -> Result<GroupAccess, HttpResponse> {
+    match select_group_access(group_id, user_id, data).await {
+        Ok(Some(access)) if access.is_owner || (access.is_member && !owner_only) => Ok(access),
+        Ok(Some(access)) if access.is_member => Err(HttpResponse::Forbidden()
+            .json(json!({"status": "fail","message": "Only the owner may change the group"}))),
+        Ok(_) => Err(group_not_found(group_id)),
+        Err(e) => Err(group_error(e)),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created, the caller is its owner and first member", body = GetIdSchema),
+(status = 400, description = "Missing or too long name", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 409, description = "Name taken by another group of the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateGroup),
+)]
+#[post("/groups")]
+pub async fn create_group(
+    user: AuthUser,
+    body: web::Json<CreateGroup>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "name must have between 1 and 100 characters"}));
+    }
+    match insert_group(name, user.id, &data).await {
+        Ok(id) => HttpResponse::Created().json(GetIdSchema { id }),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, groups the caller owns or belongs to", body = Vec<GroupModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/groups")]
+pub async fn get_my_groups(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_groups_of_user(user.id, &data).await {
+        Ok(groups) => HttpResponse::Ok().json(groups),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted with its file grants"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the group", body = String),
+(status = 404, description = "Group not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/groups/{id}")]
+pub async fn remove_group(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let group_id = path.into_inner();
+    if let Err(response) = require_group_access(group_id, user.id, true, &data).await {
+        return response;
+    }
+    match delete_group(group_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => group_not_found(group_id),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<UserSearchResult>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Group not found or caller is no member", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/groups/{id}/members")]
+pub async fn get_group_members(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let group_id = path.into_inner();
+    if let Err(response) = require_group_access(group_id, user.id, false, &data).await {
+        return response;
+    }
+    match select_group_members(group_id, &data).await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "User is a member"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the group", body = String),
+(status = 404, description = "Group or user not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("user_id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/groups/{id}/members/{user_id}")]
+pub async fn put_group_member(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (group_id, member_id) = path.into_inner();
+    if let Err(response) = require_group_access(group_id, user.id, true, &data).await {
+        return response;
+    }
+    match insert_group_member(group_id, member_id, &data).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) if e.to_string().contains("violates foreign key constraint") => {
+            let message = format!("User with ID: {} not found", member_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Member removed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the group, which is required to remove others", body = String),
+(status = 404, description = "Group or member not found", body = String),
+(status = 409, description = "The owner cannot leave the group, delete it instead", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("user_id" = String, Path, description = "User Uuid, the caller's own to leave")
+))]
+#[delete("/groups/{id}/members/{user_id}")]
+pub async fn remove_group_member(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (group_id, member_id) = path.into_inner();
+    // everybody may leave, only the owner may remove others
+    let access = match require_group_access(group_id, user.id, member_id != user.id, &data).await {
+        Ok(access) => access,
+        Err(response) => return response,
+    };
+    if access.is_owner && member_id == user.id {
+        return HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "The owner cannot leave the group, delete it instead"}));
+    }
+    match delete_group_member(group_id, member_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("User with ID: {} is not a member", member_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, groups with access to the file", body = Vec<FileGroupGrantModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the file", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/files/{id}/groups")]
+pub async fn get_file_groups(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    match select_file_group_grants(file_id, &data).await {
+        Ok(grants) => HttpResponse::Ok().json(grants),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Members of the group have the role, unless a direct grant is stronger"),
+(status = 400, description = "Unknown role", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the file", body = String),
+(status = 404, description = "File not found, or group not found or caller is no member", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = GrantFileRole),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("group_id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/files/{id}/groups/{group_id}")]
+pub async fn grant_file_to_group(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    body: web::Json<GrantFileRole>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (file_id, group_id) = path.into_inner();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    if let Err(response) = require_group_access(group_id, user.id, false, &data).await {
+        return response;
+    }
+    match upsert_file_group_grant(file_id, group_id, &body.role, &data).await {
+        Ok(()) => HttpResponse::NoContent().finish(),
+        Err(e) if e.to_string().contains("violates foreign key constraint") => HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "role must be one of 'owner', 'download' or 'read'"})),
+        Err(e) => group_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Grant removed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the file", body = String),
+(status = 404, description = "File not found or not shared with the group", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("group_id" = String, Path, description = "Group Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/files/{id}/groups/{group_id}")]
+pub async fn revoke_file_from_group(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (file_id, group_id) = path.into_inner();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    match delete_file_group_grant(file_id, group_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("File with ID: {} is not shared with group {}", file_id, group_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => group_error(e),
+    }
+}
@@ -1,5 +1,54 @@
-use crate::users_controller::{get_user_id_by_mail, user_list_handler, create_user};
-use crate::files_controller::{create_file, delete_file, edit_file, get_file, get_private_files};
+use crate::users_controller::{
+    add_mail, create_user, edit_my_profile, get_avatar, get_my_mails, get_my_profile, get_public_profile,
+    get_user_id_by_mail, make_mail_primary, remove_avatar, remove_mail, resend_mail_verification,
+    search_user_handler, upload_avatar, user_list_handler, verify_mail_token,
+};
+use crate::files_controller::{
+    bulk_delete_files, bulk_edit_files, create_file, delete_file, download_files, edit_file, get_file,
+    get_private_files, import_files,
+};
+use crate::collections_controller::{create_collection, download_collection};
+use crate::prints_controller::{
+    create_print, edit_print, get_failure_reasons, get_print, get_print_photo_content, get_print_photos,
+    print_list_handler, remove_print, upload_print_photo,
+};
+use crate::catalog_controller::{
+    get_printer_brands, create_printer_brand, edit_printer_brand, remove_printer_brand,
+    get_material_brands, create_material_brand, edit_material_brand, remove_material_brand,
+    get_printers, get_printer, create_printer, edit_printer, remove_printer, get_materials,
+    get_material, create_material, edit_material, remove_material,
+};
+use crate::stats_controller::{
+    get_file_print_stats, get_material_print_stats, get_most_printed_files, get_printer_print_stats,
+    get_filament_report, get_recommended_settings,
+};
+use crate::queue_controller::{
+    cancel_print_job, configure_printer_connector, create_user_printer, edit_print_job_status,
+    enqueue_print_job, get_print_jobs, get_user_printers, remove_user_printer, reorder_print_jobs,
+    start_next_print_job,
+};
+use crate::auth_controller::{
+    change_password, confirm_password_reset, create_api_key, get_api_keys, login, logout,
+    request_password_reset, revoke_api_key,
+};
+use crate::oidc_controller::{oidc_callback, oidc_login};
+use crate::account_controller::{delete_my_account, export_account};
+use crate::organizations_controller::{
+    assign_file_to_organization, create_organization, get_my_organizations, get_organization_members,
+    put_organization_member, remove_file_from_organization, remove_organization,
+    remove_organization_member,
+};
+use crate::groups_controller::{
+    create_group, get_file_groups, get_group_members, get_my_groups, grant_file_to_group, put_group_member,
+    remove_group, remove_group_member, revoke_file_from_group,
+};
+use crate::admin_controller::{
+    change_user_role, delete_any_file_handler, get_abuse_reports, hide_file, report_file,
+    resolve_abuse_report_handler, suspend_user_handler, unhide_file, unsuspend_user_handler,
+};
+use crate::gcode_controller::{
+    get_filament_usage, get_gcode_layers, get_gcode_variants, link_gcode_to_model, unlink_gcode_from_model,
+};
 
 use actix_web::{get, web, HttpResponse, Responder};
 use serde_json::json;
@@ -16,11 +65,117 @@ pub fn config(conf: &mut web::ServiceConfig) {
         .service(health_checker_handler)
         .service(get_private_files)
         .service(user_list_handler)
+        .service(change_user_role)
+        .service(suspend_user_handler)
+        .service(unsuspend_user_handler)
+        .service(hide_file)
+        .service(unhide_file)
+        .service(delete_any_file_handler)
+        .service(get_abuse_reports)
+        .service(resolve_abuse_report_handler)
         .service(create_file)
         .service(get_file)
+        .service(bulk_edit_files)
+        .service(bulk_delete_files)
+        .service(download_files)
+        .service(import_files)
         .service(edit_file)
         .service(delete_file)
+        .service(report_file)
+        .service(assign_file_to_organization)
+        .service(remove_file_from_organization)
+        .service(create_organization)
+        .service(get_my_organizations)
+        .service(remove_organization)
+        .service(get_organization_members)
+        .service(put_organization_member)
+        .service(remove_organization_member)
+        .service(get_file_groups)
+        .service(grant_file_to_group)
+        .service(revoke_file_from_group)
+        .service(create_group)
+        .service(get_my_groups)
+        .service(remove_group)
+        .service(get_group_members)
+        .service(put_group_member)
+        .service(remove_group_member)
+        // before get_user_id_by_mail, which would take `me` or `search` for a mail
+        .service(get_my_profile)
+        .service(edit_my_profile)
+        .service(export_account)
+        .service(delete_my_account)
+        .service(create_api_key)
+        .service(get_api_keys)
+        .service(revoke_api_key)
+        .service(search_user_handler)
         .service(get_user_id_by_mail)
-        .service(create_user);
+        .service(create_user)
+        .service(get_public_profile)
+        .service(upload_avatar)
+        .service(remove_avatar)
+        .service(get_avatar)
+        .service(get_my_mails)
+        .service(add_mail)
+        .service(remove_mail)
+        .service(make_mail_primary)
+        .service(resend_mail_verification)
+        .service(verify_mail_token)
+        .service(login)
+        .service(logout)
+        .service(oidc_login)
+        .service(oidc_callback)
+        .service(change_password)
+        .service(request_password_reset)
+        .service(confirm_password_reset)
+        .service(create_collection)
+        .service(download_collection)
+        .service(print_list_handler)
+        .service(get_print)
+        .service(create_print)
+        .service(edit_print)
+        .service(remove_print)
+        .service(upload_print_photo)
+        .service(get_print_photos)
+        .service(get_print_photo_content)
+        .service(get_failure_reasons)
+        .service(get_printer_brands)
+        .service(create_printer_brand)
+        .service(edit_printer_brand)
+        .service(remove_printer_brand)
+        .service(get_material_brands)
+        .service(create_material_brand)
+        .service(edit_material_brand)
+        .service(remove_material_brand)
+        .service(get_printers)
+        .service(get_printer)
+        .service(create_printer)
+        .service(edit_printer)
+        .service(remove_printer)
+        .service(get_materials)
+        .service(get_material)
+        .service(create_material)
+        .service(edit_material)
+        .service(remove_material)
+        .service(get_file_print_stats)
+        .service(get_printer_print_stats)
+        .service(get_material_print_stats)
+        .service(get_most_printed_files)
+        .service(get_recommended_settings)
+        .service(get_filament_report)
+        .service(create_user_printer)
+        .service(get_user_printers)
+        .service(remove_user_printer)
+        .service(get_print_jobs)
+        .service(enqueue_print_job)
+        .service(reorder_print_jobs)
+        .service(cancel_print_job)
+        .service(edit_print_job_status)
+        .service(configure_printer_connector)
+        .service(start_next_print_job)
+        .service(get_gcode_layers)
+        .service(get_filament_usage)
+        .service(get_gcode_variants)
+        .service(link_gcode_to_model)
+        .service(unlink_gcode_from_model);
     conf.service(scope);
 }
@@ -0,0 +1,117 @@
+use futures::future::BoxFuture;
+use lettre::{
+    message::{header::ContentType, Mailbox},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct MailError(String);
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sending mail failed: {}", self.0)
+    }
+}
+
+pub trait Mailer: Send + Sync {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), MailError>>;
+}
+
+/// Picks the mailer from `MAILER`: `smtp` sends through `SMTP_HOST`, anything else logs
+/// mails to `MAIL_LOG_FILE` or, if unset, to stdout.
+pub fn from_env() -> Arc<dyn Mailer> {
+    match std::env::var("MAILER").as_deref() {
+        Ok("smtp") => Arc::new(SmtpMailer::from_env()),
+        _ => Arc::new(LogMailer {
+            path: std::env::var_os("MAIL_LOG_FILE").map(PathBuf::from),
+        }),
+    }
+}
+
+/// Writes mails instead of sending them, for development and tests.
+pub struct LogMailer {
+    path: Option<PathBuf>,
+}
+
+impl Mailer for LogMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(async move {
+            let entry = format!("To: {}\nSubject: {}\n\n{}\n---\n", mail.to, mail.subject, mail.body);
+            let path = match &self.path {
+                Some(path) => path,
+                None => {
+                    println!("📧 {}", entry);
+                    return Ok(());
+                }
+            };
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await
+                .map_err(|e| MailError(e.to_string()))?;
+            file.write_all(entry.as_bytes()).await.map_err(|e| MailError(e.to_string()))
+        })
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    /// `SMTP_TLS` is `starttls` (default), `tls` or `none`.
+    fn from_env() -> Self {
+        let host = std::env::var("SMTP_HOST").expect("SMTP_HOST must be set");
+        let from = std::env::var("MAIL_FROM")
+            .expect("MAIL_FROM must be set")
+            .parse()
+            .expect("MAIL_FROM must be a mail address");
+        let builder = match std::env::var("SMTP_TLS").as_deref() {
+            Ok("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+            Ok("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).expect("invalid SMTP_HOST"),
+            _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).expect("invalid SMTP_HOST"),
+        };
+        let builder = match std::env::var("SMTP_PORT").ok().and_then(|port| port.parse().ok()) {
+            Some(port) => builder.port(port),
+            None => builder,
+        };
+        let builder = match (std::env::var("SMTP_USERNAME"), std::env::var("SMTP_PASSWORD")) {
+            (Ok(username), Ok(password)) => builder.credentials(Credentials::new(username, password)),
+            _ => builder,
+        };
+        SmtpMailer { transport: builder.build(), from }
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send<'a>(&'a self, mail: &'a Mail) -> BoxFuture<'a, Result<(), MailError>> {
+        Box::pin(async move {
+            let to: Mailbox = mail.to.parse().map_err(|e| MailError(format!("{}", e)))?;
+            let message = Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject(mail.subject.as_str())
+                .header(ContentType::TEXT_PLAIN)
+                .body(mail.body.clone())
+                .map_err(|e| MailError(e.to_string()))?;
+            self.transport
+                .send(message)
+                .await
+                .map(|_| ())
+                .map_err(|e| MailError(e.to_string()))
+        })
+    }
+}
@@ -1,10 +1,32 @@
+mod account_controller;
+mod admin_controller;
+mod auth;
+mod auth_controller;
+mod bundle;
+mod connector;
+mod etag;
+mod gcode_preview;
+mod groups_controller;
+mod mailer;
 mod model;
+mod oidc;
+mod oidc_controller;
+mod organizations_controller;
+mod password;
 mod schema;
 mod handler;
 mod prints_controller;
 mod files_controller;
 mod users_controller;
+mod collections_controller;
+mod catalog_controller;
+mod stats_controller;
+mod queue_controller;
+mod gcode_controller;
 mod query_service;
+mod recommendation;
+mod storage;
+mod token;
 
 use actix_cors::Cors;
 use actix_web::middleware::Logger;
@@ -16,11 +38,30 @@ use model::*;
 use schema::*;
 use users_controller::*;
 use files_controller::*;
+use collections_controller::*;
+use prints_controller::*;
+use catalog_controller::*;
+use stats_controller::*;
+use queue_controller::*;
+use auth_controller::*;
+use account_controller::*;
+use admin_controller::*;
+use organizations_controller::*;
+use groups_controller::*;
+use oidc_controller::*;
+use gcode_controller::*;
+use mailer::Mailer;
+use std::sync::Arc;
+use storage::Storage;
 use utoipa::{OpenApi};
 
 
 pub struct AppState {
     db: Pool<Postgres>,
+    storage: Storage,
+    mailer: Arc<dyn Mailer>,
+    /// `None` if OIDC login is not configured
+    oidc: Option<oidc::OidcClient>,
 }
 
 
@@ -48,6 +89,20 @@ async fn main() -> std::io::Result<()> {
         }
     };
 
+    let storage = Storage::from_env();
+    let mailer = mailer::from_env();
+    let oidc = oidc::OidcClient::from_env();
+    let app_state = web::Data::new(AppState { db: pool, storage, mailer, oidc });
+
+    let poll_seconds = std::env::var("CONNECTOR_POLL_SECONDS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(10);
+    actix_web::rt::spawn(connector::run_poller(
+        app_state.clone(),
+        std::time::Duration::from_secs(poll_seconds),
+    ));
+
     println!("🚀 Server started successfully");
 
     #[derive(OpenApi)]
@@ -59,15 +114,194 @@ async fn main() -> std::io::Result<()> {
             create_file,
             delete_file,
             edit_file,
+            bulk_edit_files,
+            bulk_delete_files,
+            download_files,
+            import_files,
+            create_collection,
+            download_collection,
+            print_list_handler,
+            get_print,
+            create_print,
+            edit_print,
+            remove_print,
+            upload_print_photo,
+            get_print_photos,
+            get_print_photo_content,
+            get_failure_reasons,
+            get_printer_brands,
+            create_printer_brand,
+            edit_printer_brand,
+            remove_printer_brand,
+            get_material_brands,
+            create_material_brand,
+            edit_material_brand,
+            remove_material_brand,
+            get_printers,
+            get_printer,
+            create_printer,
+            edit_printer,
+            remove_printer,
+            get_materials,
+            get_material,
+            create_material,
+            edit_material,
+            remove_material,
+            get_file_print_stats,
+            get_printer_print_stats,
+            get_material_print_stats,
+            get_most_printed_files,
+            get_recommended_settings,
+            get_filament_report,
+            create_user_printer,
+            get_user_printers,
+            remove_user_printer,
+            get_print_jobs,
+            enqueue_print_job,
+            reorder_print_jobs,
+            cancel_print_job,
+            edit_print_job_status,
+            configure_printer_connector,
+            start_next_print_job,
+            get_gcode_layers,
+            get_filament_usage,
+            get_gcode_variants,
+            link_gcode_to_model,
+            unlink_gcode_from_model,
+            user_list_handler,
+            search_user_handler,
             get_user_id_by_mail,
-            create_user
+            create_user,
+            get_my_profile,
+            edit_my_profile,
+            export_account,
+            delete_my_account,
+            get_public_profile,
+            upload_avatar,
+            remove_avatar,
+            get_avatar,
+            get_my_mails,
+            add_mail,
+            remove_mail,
+            make_mail_primary,
+            resend_mail_verification,
+            verify_mail_token,
+            login,
+            logout,
+            change_password,
+            request_password_reset,
+            confirm_password_reset,
+            create_api_key,
+            get_api_keys,
+            revoke_api_key,
+            change_user_role,
+            suspend_user_handler,
+            unsuspend_user_handler,
+            hide_file,
+            unhide_file,
+            delete_any_file_handler,
+            report_file,
+            get_abuse_reports,
+            resolve_abuse_report_handler,
+            create_organization,
+            get_my_organizations,
+            remove_organization,
+            get_organization_members,
+            put_organization_member,
+            remove_organization_member,
+            assign_file_to_organization,
+            remove_file_from_organization,
+            create_group,
+            get_my_groups,
+            remove_group,
+            get_group_members,
+            put_group_member,
+            remove_group_member,
+            get_file_groups,
+            grant_file_to_group,
+            revoke_file_from_group,
+            oidc_login,
+            oidc_callback
         ),
         components(schemas(
             UpdateFile,
             CreateFile,
             IdSchema,
             CreateUser,
-            FileResponse
+            FileResponse,
+            BulkUpdateFiles,
+            BulkFileIds,
+            BulkItemResult,
+            CreateCollection,
+            ImportItemResult,
+            PrintModel,
+            CreatePrint,
+            UpdatePrint,
+            MaterialType,
+            BrandModel,
+            PrinterCatalogModel,
+            MaterialCatalogModel,
+            CreateBrand,
+            CreatePrinter,
+            UpdatePrinter,
+            CreateMaterial,
+            UpdateMaterial,
+            PrintStatsModel,
+            FilePrintCountModel,
+            SettingRange,
+            RecommendedSettings,
+            FailureCategory,
+            PrintPhotoModel,
+            FailureReasonCount,
+            JobStatus,
+            UserPrinterModel,
+            PrintJobModel,
+            CreateUserPrinter,
+            CreatePrintJob,
+            ReorderPrintJobs,
+            UpdatePrintJobStatus,
+            ConnectorKind,
+            ConnectorSettings,
+            GcodeLayer,
+            GcodeLayers,
+            FilamentUsage,
+            MonthlyFilamentUsage,
+            GcodeVariantModel,
+            LinkGcodeVariant,
+            UserMailModel,
+            AddMail,
+            SessionModel,
+            Login,
+            ChangePassword,
+            PasswordResetRequest,
+            PasswordResetConfirm,
+            UserProfileModel,
+            PublicProfileModel,
+            UpdateProfile,
+            UserModel,
+            UserSearchResult,
+            CollectionModel,
+            DeleteAccount,
+            ApiKeyScope,
+            ApiKeyModel,
+            CreatedApiKeyModel,
+            CreateApiKey,
+            UserRole,
+            AbuseReportModel,
+            UpdateUserRole,
+            SuspendUser,
+            CreateAbuseReport,
+            OrganizationRole,
+            OrganizationModel,
+            OrganizationMemberModel,
+            CreateOrganization,
+            UpdateOrganizationMember,
+            AssignFileOrganization,
+            GroupModel,
+            FileGroupGrantModel,
+            CreateGroup,
+            GrantFileRole,
+            UserIdentityModel
         ))
     )]
     struct ApiDoc;
@@ -80,7 +314,8 @@ async fn main() -> std::io::Result<()> {
             .allow_any_method()
             .supports_credentials();
         App::new()
-            .app_data(web::Data::new(AppState { db: pool.clone() }))
+            .app_data(app_state.clone())
+            .app_data(web::PayloadConfig::new(bundle::MAX_IMPORT_BYTES))
             .configure(handler::config)
             .wrap(cors)
             .wrap(Logger::default())
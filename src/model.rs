@@ -17,6 +17,7 @@ pub struct FilePublicResponseModel {
     pub owner: Option<String>,
     #[serde(rename = "isDownloadable")]
     pub is_downloadable: bool,
+    pub version: i32,
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
@@ -32,6 +33,7 @@ pub struct FilePrivateResponseModel {
     pub owner: String,
     #[serde(rename = "isDownloadable")]
     pub is_downloadable: Option<bool>,
+    pub version: i32,
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
@@ -48,6 +50,9 @@ pub struct FileResponseModel {
     pub is_downloadable: bool,
     #[serde(rename = "isPublic")]
     pub is_public: bool,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub version: i32,
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
@@ -65,12 +70,28 @@ pub struct FileResponse {
     pub is_downloadable: Option<bool>,
     #[serde(rename = "isPublic")]
     pub is_public: Option<bool>,
+    pub description: Option<String>,
+    pub license: Option<String>,
+    pub version: i32,
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
 pub struct UserModel {
     pub id: Uuid,
     pub user_name: String,
+    pub display_name: Option<String>,
+    pub primary_mail: Option<String>,
+    pub role: UserRole,
+    pub suspended: Option<chrono::DateTime<chrono::Utc>>,
+    pub suspension_reason: Option<String>,
+}
+
+/// What other users may learn about an account, e.g. in sharing dialogs.
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserSearchResult {
+    pub id: Uuid,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
 }
 
 #[derive(Debug, FromRow, Deserialize, Serialize, ToSchema)]
@@ -85,4 +106,530 @@ pub struct PrintModel {
     pub filament_type: Option<String>,
     pub printer: Option<String>,
     pub gcode_id: Uuid,
+    pub material_id: Option<Uuid>,
+    pub printer_id: Option<Uuid>,
+    pub owner_id: Option<Uuid>,
+    pub failure_category: Option<FailureCategory>,
+    pub notes: Option<String>,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    /// Derived from the G-code filament length and the material, `null` if either is unknown
+    pub filament_grams: Option<f64>,
+    /// At the current material price
+    pub filament_cost: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct BulkItemResult {
+    pub id: Uuid,
+    pub status: String,
+    pub message: Option<String>,
+}
+
+impl BulkItemResult {
+    pub fn ok(id: Uuid) -> Self {
+        BulkItemResult { id, status: "success".to_string(), message: None }
+    }
+
+    pub fn fail(id: Uuid, message: impl Into<String>) -> Self {
+        BulkItemResult { id, status: "fail".to_string(), message: Some(message.into()) }
+    }
+
+    pub fn error(id: Uuid, message: impl Into<String>) -> Self {
+        BulkItemResult { id, status: "error".to_string(), message: Some(message.into()) }
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone)]
+pub struct DownloadableFile {
+    pub id: Uuid,
+    pub fullname: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ImportItemResult {
+    pub name: String,
+    pub status: String,
+    pub id: Option<Uuid>,
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Option<Uuid>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "material_type")]
+pub enum MaterialType {
+    #[sqlx(rename = "PLA")]
+    #[serde(rename = "PLA")]
+    Pla,
+    #[sqlx(rename = "PETG")]
+    #[serde(rename = "PETG")]
+    Petg,
+    #[sqlx(rename = "ABS")]
+    #[serde(rename = "ABS")]
+    Abs,
+    Composite,
+    Resin,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct BrandModel {
+    pub id: Uuid,
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PrinterCatalogModel {
+    pub id: Uuid,
+    pub model: String,
+    #[serde(rename = "brandId")]
+    pub brand_id: Uuid,
+    pub brand: String,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct MaterialCatalogModel {
+    pub id: Uuid,
+    pub description: String,
+    #[serde(rename = "matType")]
+    pub mat_type: MaterialType,
+    #[serde(rename = "brandId")]
+    pub brand_id: Uuid,
+    pub brand: String,
+    #[serde(rename = "densityGCm3")]
+    pub density_g_cm3: Option<f64>,
+    #[serde(rename = "diameterMm")]
+    pub diameter_mm: f64,
+    #[serde(rename = "pricePerKg")]
+    pub price_per_kg: Option<f64>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PrintStatsModel {
+    pub prints: i64,
+    #[serde(rename = "successfulPrints")]
+    pub successful_prints: i64,
+    #[serde(rename = "successRate")]
+    pub success_rate: Option<f64>,
+    #[serde(rename = "mostUsedPrinterId")]
+    pub most_used_printer_id: Option<Uuid>,
+    #[serde(rename = "mostUsedPrinter")]
+    pub most_used_printer: Option<String>,
+    #[serde(rename = "mostUsedMaterialId")]
+    pub most_used_material_id: Option<Uuid>,
+    #[serde(rename = "mostUsedMaterial")]
+    pub most_used_material: Option<String>,
+    #[serde(rename = "minBedTempCelsius")]
+    pub min_bed_temp_celsius: Option<i32>,
+    #[serde(rename = "maxBedTempCelsius")]
+    pub max_bed_temp_celsius: Option<i32>,
+    #[serde(rename = "minExtruderTemp")]
+    pub min_extruder_temp: Option<i32>,
+    #[serde(rename = "maxExtruderTemp")]
+    pub max_extruder_temp: Option<i32>,
+    #[serde(rename = "minNozzleSizeMm")]
+    pub min_nozzle_size_mm: Option<f64>,
+    #[serde(rename = "maxNozzleSizeMm")]
+    pub max_nozzle_size_mm: Option<f64>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct FilePrintCountModel {
+    pub id: Uuid,
+    pub fullname: String,
+    pub prints: i64,
+    #[serde(rename = "successfulPrints")]
+    pub successful_prints: i64,
+    #[serde(rename = "successRate")]
+    pub success_rate: Option<f64>,
+}
+
+#[derive(Debug, FromRow, Clone)]
+pub struct PrintSettingsRow {
+    pub printer_id: Option<Uuid>,
+    pub material_id: Option<Uuid>,
+    pub nozzle_size_mm: Option<f64>,
+    pub bed_temp_celsius: Option<i32>,
+    pub extruder_temp: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct SettingRange {
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct RecommendedSettings {
+    pub samples: usize,
+    #[serde(rename = "matchingPrinter")]
+    pub matching_printer: usize,
+    #[serde(rename = "matchingMaterial")]
+    pub matching_material: usize,
+    #[serde(rename = "nozzleSizeMm")]
+    pub nozzle_size_mm: Option<SettingRange>,
+    #[serde(rename = "bedTempCelsius")]
+    pub bed_temp_celsius: Option<SettingRange>,
+    #[serde(rename = "extruderTemp")]
+    pub extruder_temp: Option<SettingRange>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "failure_category", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum FailureCategory {
+    Warping,
+    Stringing,
+    LayerShift,
+    Adhesion,
+    Spaghetti,
+    Other,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PrintPhotoModel {
+    pub id: Uuid,
+    #[serde(rename = "printId")]
+    pub print_id: Uuid,
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    pub sizebytes: i64,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct FailureReasonCount {
+    /// `None` for failed prints without a recorded category
+    pub category: Option<FailureCategory>,
+    pub prints: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "job_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Printing,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn can_transition_to(self, next: JobStatus) -> bool {
+        matches!(
+            (self, next),
+            (JobStatus::Queued, JobStatus::Printing)
+                | (JobStatus::Queued, JobStatus::Cancelled)
+                | (JobStatus::Printing, JobStatus::Done)
+                | (JobStatus::Printing, JobStatus::Failed)
+                | (JobStatus::Printing, JobStatus::Cancelled)
+        )
+    }
+
+    /// A job that finished this way gets a `print` record.
+    pub fn is_finished_print(self) -> bool {
+        matches!(self, JobStatus::Done | JobStatus::Failed)
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserPrinterModel {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "printerId")]
+    pub printer_id: Uuid,
+    pub printer: String,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "connectorKind")]
+    pub connector_kind: Option<ConnectorKind>,
+    #[serde(rename = "connectorUrl")]
+    pub connector_url: Option<String>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PrintJobModel {
+    pub id: Uuid,
+    #[serde(rename = "userPrinterId")]
+    pub user_printer_id: Uuid,
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Uuid,
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    pub priority: i32,
+    pub position: i32,
+    pub status: JobStatus,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub started: Option<chrono::DateTime<chrono::Utc>>,
+    pub finished: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "printId")]
+    pub print_id: Option<Uuid>,
+    /// Completion in percent as reported by the printer connector
+    pub progress: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "connector_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectorKind {
+    OctoPrint,
+    Moonraker,
+}
+
+/// Toolpath of one layer. Every polyline is a flat `[x0, y0, x1, y1, ...]` list in millimetres.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct GcodeLayer {
+    pub index: usize,
+    pub z: f32,
+    pub extrusions: Vec<Vec<f32>>,
+    pub travels: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct GcodeLayers {
+    #[serde(rename = "layerCount")]
+    pub layer_count: usize,
+    pub layers: Vec<GcodeLayer>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct FilamentUsage {
+    #[serde(rename = "lengthMm")]
+    pub length_mm: Option<f64>,
+    pub grams: Option<f64>,
+    pub cost: Option<f64>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct MonthlyFilamentUsage {
+    /// First day of the month
+    pub month: chrono::NaiveDate,
+    pub prints: i64,
+    pub grams: Option<f64>,
+    pub cost: Option<f64>,
+    /// Prints whose G-code length or material properties are unknown and are not included
+    #[serde(rename = "unknownPrints")]
+    pub unknown_prints: i64,
+}
+
+/// A G-code sliced from a model file, with the outcomes of its prints.
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct GcodeVariantModel {
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Uuid,
+    #[serde(rename = "fileId")]
+    pub file_id: Uuid,
+    pub fullname: String,
+    pub label: Option<String>,
+    #[serde(rename = "printerId")]
+    pub printer_id: Option<Uuid>,
+    pub printer: Option<String>,
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    pub material: Option<String>,
+    #[serde(rename = "isDownloadable")]
+    pub is_downloadable: bool,
+    #[serde(rename = "filamentLengthMm")]
+    pub filament_length_mm: Option<f64>,
+    pub prints: i64,
+    #[serde(rename = "successfulPrints")]
+    pub successful_prints: i64,
+    #[serde(rename = "successRate")]
+    pub success_rate: Option<f64>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserMailModel {
+    pub mail: String,
+    #[serde(rename = "isVerified")]
+    pub is_verified: bool,
+    #[serde(rename = "isPrimary")]
+    pub is_primary: bool,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Returned once on login, only the hash of the token is stored.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct SessionModel {
+    pub token: String,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserProfileModel {
+    pub id: Uuid,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    /// The avatar is served at `/api/users/{id}/avatar`
+    #[serde(rename = "hasAvatar")]
+    pub has_avatar: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PublicProfileModel {
+    pub id: Uuid,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+    #[serde(rename = "hasAvatar")]
+    pub has_avatar: bool,
+    /// Prints the user logged
+    #[serde(rename = "printsMade")]
+    pub prints_made: i64,
+    /// Prints anybody logged of the user's public files
+    #[serde(rename = "printsOfFiles")]
+    pub prints_of_files: i64,
+    /// Average over the user's rated public files
+    #[serde(rename = "averageRating")]
+    pub average_rating: Option<f64>,
+    #[serde(rename = "publicFiles")]
+    pub public_files: Vec<FilePublicResponseModel>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct CollectionModel {
+    pub id: Uuid,
+    pub name: String,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "fileIds")]
+    pub file_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "api_key_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    /// Reading anything the user can see
+    Read,
+    /// Uploading and importing files and linking G-code variants
+    Upload,
+    /// Every other change
+    Manage,
+}
+
+impl sqlx::postgres::PgHasArrayType for ApiKeyScope {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_api_key_scope")
+    }
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ApiKeyModel {
+    pub id: Uuid,
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "lastUsed")]
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Returned once on creation, only the hash of the token is stored.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct CreatedApiKeyModel {
+    pub token: String,
+    #[serde(rename = "apiKey")]
+    pub api_key: ApiKeyModel,
+}
+
+/// Global role of an account, ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "user_role", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum UserRole {
+    User,
+    /// Reviews abuse reports and hides files
+    Moderator,
+    /// Additionally manages users, the catalog and deletes files
+    Admin,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct AbuseReportModel {
+    pub id: Uuid,
+    #[serde(rename = "fileId")]
+    pub file_id: Uuid,
+    pub fullname: String,
+    #[serde(rename = "isHidden")]
+    pub is_hidden: bool,
+    #[serde(rename = "reporterId")]
+    pub reporter_id: Option<Uuid>,
+    pub reason: String,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub resolved: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "resolvedBy")]
+    pub resolved_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type, Deserialize, Serialize, ToSchema)]
+#[sqlx(type_name = "organization_role", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum OrganizationRole {
+    /// Manages members and acts as owner of the organization's files
+    Admin,
+    /// May download the organization's files and add own files to it
+    Member,
+    /// May read the organization's files
+    Guest,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct OrganizationModel {
+    pub id: Uuid,
+    pub name: String,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    /// Role of the caller
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct OrganizationMemberModel {
+    #[serde(rename = "userId")]
+    pub user_id: Uuid,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub role: OrganizationRole,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct GroupModel {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(rename = "ownerId")]
+    pub owner_id: Uuid,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    pub members: i64,
+}
+
+/// A group's access to a file.
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct FileGroupGrantModel {
+    #[serde(rename = "groupId")]
+    pub group_id: Uuid,
+    pub name: String,
+    /// 'owner', 'download' or 'read'
+    pub role: String,
+}
+
+/// An account at an OpenID Connect provider linked to the user.
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone, ToSchema)]
+pub struct UserIdentityModel {
+    pub issuer: String,
+    pub subject: String,
+    pub created: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(rename = "lastLogin")]
+    pub last_login: Option<chrono::DateTime<chrono::Utc>>,
 }
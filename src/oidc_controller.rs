@@ -0,0 +1,154 @@
+use crate::{
+    model::SessionModel,
+    oidc::{IdTokenClaims, OidcError},
+    query_service::{auth_queries::insert_session, user_queries::normalize_mail},
+    schema::OidcCallback,
+    token, AppState,
+};
+use actix_web::{get, http::header, web, HttpResponse, Responder};
+use serde_json::json;
+use crate::query_service::oidc_queries::*;
+
+fn oidc_error(e: impl std::fmt::Debug) -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn provider_failed(e: OidcError) -> HttpResponse {
+    match e {
+        OidcError::Provider(_) => HttpResponse::BadGateway()
+            .json(json!({"status": "error","message": e.to_string()})),
+        OidcError::InvalidToken(_) => HttpResponse::Unauthorized()
+            .json(json!({"status": "fail","message": e.to_string()})),
+    }
+}
+
+fn not_configured() -> HttpResponse {
+    HttpResponse::NotFound().json(json!({"status": "fail","message": "OIDC login is not configured"}))
+}
+
+fn truncate(value: &str, max_chars: usize) -> String {
+    value.trim().chars().take(max_chars).collect()
+}
+
+/// User name for an account created on first login: the provider's preferred user name,
+/// otherwise the local part of the mail.
+fn user_name_for(claims: &IdTokenClaims, mail: &str) -> String {
+    let preferred = claims.preferred_username.as_deref().filter(|name| !name.trim().is_empty());
+    let local_part = mail.split('@').next().unwrap_or(mail);
+    truncate(preferred.unwrap_or(local_part), 100)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 302, description = "Redirect to the provider's login, which redirects back to the callback"),
+(status = 404, description = "OIDC login is not configured", body = String),
+(status = 500, description = "Internal server error", body = String),
+(status = 502, description = "Provider not reachable", body = String)
+))]
+#[get("/auth/oidc/login")]
+pub async fn oidc_login(
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let client = match data.oidc.as_ref() {
+        Some(client) => client,
+        None => return not_configured(),
+    };
+    let state = token::generate();
+    let nonce = token::generate();
+    let code_verifier = token::generate();
+    let location = match client.authorization_url(&state, &nonce, &code_verifier).await {
+        Ok(location) => location,
+        Err(e) => return provider_failed(e),
+    };
+    if let Err(e) = insert_login_state(&token::hash(&state), &nonce, &code_verifier, &data).await {
+        return oidc_error(e);
+    }
+    HttpResponse::Found().insert_header((header::LOCATION, location)).finish()
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Logged in, send the token as `Authorization: Bearer <token>`. On first login \
+the identity is linked to the account with the same verified mail, or an account is created.", body = SessionModel),
+(status = 400, description = "Login was denied at the provider, or code or state is missing, unknown or expired", body = String),
+(status = 401, description = "ID token is invalid", body = String),
+(status = 403, description = "Account is suspended, or the provider did not supply a verified mail on first login", body = String),
+(status = 404, description = "OIDC login is not configured", body = String),
+(status = 500, description = "Internal server error", body = String),
+(status = 502, description = "Provider not reachable", body = String)
+),
+params(
+("code" = Option<String>, Query, description = "Authorization code"),
+("state" = Option<String>, Query, description = "State passed to the provider on login"),
+("error" = Option<String>, Query, description = "Set by the provider if the login failed")
+))]
+#[get("/auth/oidc/callback")]
+pub async fn oidc_callback(
+    query: web::Query<OidcCallback>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let client = match data.oidc.as_ref() {
+        Some(client) => client,
+        None => return not_configured(),
+    };
+    let query = query.into_inner();
+    if let Some(error) = query.error {
+        let message = format!("Login failed at the provider: {}", query.error_description.unwrap_or(error));
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    let (code, state) = match (query.code, query.state) {
+        (Some(code), Some(state)) => (code, state),
+        _ => {
+            return HttpResponse::BadRequest()
+                .json(json!({"status": "fail","message": "code and state are required"}));
+        }
+    };
+    let login_state = match take_login_state(&token::hash(&state), &data).await {
+        Ok(Some(login_state)) => login_state,
+        Ok(None) => {
+            return HttpResponse::BadRequest()
+                .json(json!({"status": "fail","message": "Login expired or already finished, start again"}));
+        }
+        Err(e) => return oidc_error(e),
+    };
+    let claims = match client.exchange_code(&code, &login_state.code_verifier, &login_state.nonce).await {
+        Ok(claims) => claims,
+        Err(e) => return provider_failed(e),
+    };
+
+    // only mails the provider vouches for may link to or register an account
+    let mail = claims.email.as_deref()
+        .filter(|_| claims.email_verified)
+        .and_then(normalize_mail);
+    let user_name = user_name_for(&claims, mail.as_deref().unwrap_or_default());
+    let display_name = claims.name.as_deref().map(|name| truncate(name, 100)).filter(|name| !name.is_empty());
+    let login = login_identity(
+        &client.issuer,
+        &claims.sub,
+        mail.as_deref(),
+        &user_name,
+        display_name.as_deref(),
+        &data,
+    ).await;
+    let user_id = match login {
+        Ok(IdentityLogin::User { suspended: true, .. }) => {
+            return HttpResponse::Forbidden()
+                .json(json!({"status": "fail","message": "Account is suspended"}));
+        }
+        Ok(IdentityLogin::User { user_id, .. }) => user_id,
+        Ok(IdentityLogin::MailRequired) => {
+            return HttpResponse::Forbidden()
+                .json(json!({"status": "fail","message": "The provider did not supply a verified mail"}));
+        }
+        Err(e) => return oidc_error(e),
+    };
+
+    let session_token = token::generate();
+    match insert_session(user_id, &token::hash(&session_token), &data).await {
+        Ok(expires) => HttpResponse::Created().json(SessionModel { token: session_token, expires }),
+        Err(e) => oidc_error(e),
+    }
+}
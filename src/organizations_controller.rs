@@ -0,0 +1,298 @@
+use crate::{
+    auth::AuthUser,
+    model::OrganizationRole,
+    files_controller::{file_not_found, require_file_owner},
+    schema::{AssignFileOrganization, CreateOrganization, UpdateOrganizationMember},
+    AppState, GetIdSchema,
+};
+use actix_web::{delete, get, post, put, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::organization_queries::*;
+
+fn organization_error(e: sqlx::Error) -> HttpResponse {
+    let message = e.to_string();
+    if message.contains("duplicate key") {
+        return HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "An organization with this name already exists"}));
+    }
+    if message.contains("violates foreign key constraint") {
+        return HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "Organization still owns files, move them out first"}));
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn organization_not_found(organization_id: Uuid) -> HttpResponse {
+    let message = format!("Organization with ID: {} not found", organization_id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+/// The caller's role in the organization if it is one of `allowed`. Non-members get a 404,
+/// so organizations are not revealed to outsiders.
+async fn require_organization_role(
+    organization_id: Uuid,
+    user_id: Uuid,
+    allowed: &[OrganizationRole],
+    data: &web::Data<AppState>,
+) -> Result<OrganizationRole, HttpResponse> {
+    match select_organization_role(organization_id, user_id, data).await {
+        Ok(Some(role)) if allowed.contains(&role) => Ok(role),
+        Ok(Some(_)) => Err(HttpResponse::Forbidden()
+            .json(json!({"status": "fail","message": "Insufficient organization role"}))),
+        Ok(None) => Err(organization_not_found(organization_id)),
+        Err(e) => Err(organization_error(e)),
+    }
+}
+
+fn member_update_response(update: Result<MemberUpdate, sqlx::Error>, user_id: Uuid) -> HttpResponse {
+    match update {
+        Ok(MemberUpdate::Updated) => HttpResponse::NoContent().finish(),
+        Ok(MemberUpdate::NotFound) => {
+            let message = format!("User with ID: {} is not a member", user_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Ok(MemberUpdate::LastAdmin) => HttpResponse::Conflict()
+            .json(json!({"status": "fail","message": "An organization needs at least one admin"})),
+        Err(e) if e.to_string().contains("violates foreign key constraint") => {
+            let message = format!("User with ID: {} not found", user_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created, the caller is its admin", body = GetIdSchema),
+(status = 400, description = "Missing or too long name", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 409, description = "Name taken", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateOrganization),
+)]
+#[post("/organizations")]
+pub async fn create_organization(
+    user: AuthUser,
+    body: web::Json<CreateOrganization>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "name must have between 1 and 100 characters"}));
+    }
+    match insert_organization(name, user.id, &data).await {
+        Ok(id) => HttpResponse::Created().json(GetIdSchema { id }),
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, organizations of the caller", body = Vec<OrganizationModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/organizations")]
+pub async fn get_my_organizations(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_organizations_of_user(user.id, &data).await {
+        Ok(organizations) => HttpResponse::Ok().json(organizations),
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Organization admin role required", body = String),
+(status = 404, description = "Organization not found", body = String),
+(status = 409, description = "Organization still owns files", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Organization Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/organizations/{id}")]
+pub async fn remove_organization(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    if let Err(response) =
+        require_organization_role(organization_id, user.id, &[OrganizationRole::Admin], &data).await
+    {
+        return response;
+    }
+    match delete_organization(organization_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => organization_not_found(organization_id),
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<OrganizationMemberModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Organization not found or caller is no member", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Organization Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/organizations/{id}/members")]
+pub async fn get_organization_members(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let organization_id = path.into_inner();
+    let everyone = [OrganizationRole::Admin, OrganizationRole::Member, OrganizationRole::Guest];
+    if let Err(response) = require_organization_role(organization_id, user.id, &everyone, &data).await {
+        return response;
+    }
+    match select_members(organization_id, &data).await {
+        Ok(members) => HttpResponse::Ok().json(members),
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Member added or role changed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Organization admin role required", body = String),
+(status = 404, description = "Organization or user not found", body = String),
+(status = 409, description = "The organization would have no admin left", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdateOrganizationMember),
+params(
+("id" = String, Path, description = "Organization Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("user_id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/organizations/{id}/members/{user_id}")]
+pub async fn put_organization_member(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    body: web::Json<UpdateOrganizationMember>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (organization_id, member_id) = path.into_inner();
+    if let Err(response) =
+        require_organization_role(organization_id, user.id, &[OrganizationRole::Admin], &data).await
+    {
+        return response;
+    }
+    member_update_response(upsert_member(organization_id, member_id, body.role, &data).await, member_id)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Member removed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Organization admin role required to remove others", body = String),
+(status = 404, description = "Organization or member not found", body = String),
+(status = 409, description = "The organization would have no admin left", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Organization Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("user_id" = String, Path, description = "User Uuid, the caller's own to leave")
+))]
+#[delete("/organizations/{id}/members/{user_id}")]
+pub async fn remove_organization_member(
+    user: AuthUser,
+    path: web::Path<(Uuid, Uuid)>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let (organization_id, member_id) = path.into_inner();
+    // everybody may leave, only admins may remove others
+    let allowed: &[OrganizationRole] = if member_id == user.id {
+        &[OrganizationRole::Admin, OrganizationRole::Member, OrganizationRole::Guest]
+    } else {
+        &[OrganizationRole::Admin]
+    };
+    if let Err(response) = require_organization_role(organization_id, user.id, allowed, &data).await {
+        return response;
+    }
+    member_update_response(delete_member(organization_id, member_id, &data).await, member_id)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "File now belongs to the organization"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the file, or only a guest of the organization", body = String),
+(status = 404, description = "File or organization not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = AssignFileOrganization),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/files/{id}/organization")]
+pub async fn assign_file_to_organization(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<AssignFileOrganization>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    let members = [OrganizationRole::Admin, OrganizationRole::Member];
+    if let Err(response) = require_organization_role(body.organization_id, user.id, &members, &data).await {
+        return response;
+    }
+    match assign_file_organization(file_id, body.organization_id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => file_not_found(file_id),
+        Err(e) => organization_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "File no longer belongs to an organization"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Not owner of the file", body = String),
+(status = 404, description = "File not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/files/{id}/organization")]
+pub async fn remove_file_from_organization(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_owner(file_id, user.id, &data).await {
+        return response;
+    }
+    match remove_file_organization(file_id, user.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => file_not_found(file_id),
+        Err(e) => organization_error(e),
+    }
+}
@@ -1,55 +1,394 @@
-use crate::model::PrintModel;
-use crate::{schema::FilterOptions, AppState};
-use actix_web::{get, web, HttpResponse, Responder};
+use crate::{
+    auth::AuthUser,
+    schema::{CreatePrint, FilterOptions, UpdatePrint},
+    storage::{IMAGE_CONTENT_TYPES, MAX_IMAGE_BYTES},
+    AppState,
+};
+use actix_web::{delete, get, http::header, patch, post, web, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
 use uuid::Uuid;
+use crate::query_service::gcode_queries::{is_file_visible, select_downloadable_gcode_file};
+use crate::query_service::print_queries::*;
 
-#[get("/prints/{id}")]
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<PrintModel>),
+(status = 404, description = "File not found or not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/files/{id}/prints")]
 pub async fn print_list_handler(
-    path: web::Path<String>,
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
     opts: web::Query<FilterOptions>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let file_id = path.into_inner();
-    let id = Uuid::parse_str(&file_id).unwrap_or(Uuid::new_v4());
-
-
+    let id = path.into_inner();
+    if let Err(response) = require_file_visible(id, user, &data).await {
+        return response;
+    }
     let limit = opts.limit.unwrap_or(10);
-    let offset = (opts.page.unwrap_or(1) - 1) * limit;
-
-    let query_result = sqlx::query_as!(
-        PrintModel,
-        "select pr.id as id, nozzle_size_mm, bed_temp_celsius, extruder_temp, successful,
-            concat(mb.full_name, ' ', m.description) as filament,
-            concat(mat_type, '') as filament_type, concat(pb.full_name, ' ', model) as printer, g.id as gcode_id
-        from print pr
-            left join material m on m.id = pr.material_fk
-            left join printer p on p.id = pr.printer_fk
-            left join gcode g on g.id = pr.gcode_fk
-            left join material_brand mb on mb.id = m.material_brand_fk
-            LEFT JOIN printer_brand pb on pb.id = p.printer_brand_fk
-            left join file f on f.id = g.file_pk
-        where f.id = $1
-        ORDER by id LIMIT $2 OFFSET $3",
-        id,
-        limit as i32,
-        offset as i32,
-        )
-        .fetch_all(&data.db)
-        .await;
+    let offset = (opts.page.unwrap_or(1).max(1) - 1) * limit;
+
+    let query_result = select_prints_for_file(id, limit, offset, &data).await;
 
     if query_result.is_err() {
-        let message = "Something bad happened while fetching all note items";
+        let message = "Something bad happened while fetching all print items";
         return HttpResponse::InternalServerError()
             .json(json!({"status": "error","message": message}));
     }
 
-    let notes = query_result.unwrap();
+    let prints = query_result.unwrap();
 
     let json_response = json!({
         "status": "success",
-        "results": notes.len(),
-        "prints": notes
+        "results": prints.len(),
+        "prints": prints
     });
     HttpResponse::Ok().json(json_response)
 }
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintModel),
+(status = 404, description = "Print not found, or its file is not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Print Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/prints/{id}")]
+pub async fn get_print(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let print_id = path.into_inner();
+    if let Err(response) = require_print_visible(print_id, user, &data).await {
+        return response;
+    }
+    match select_print(print_id, &data).await {
+        Ok(Some(print)) => HttpResponse::Ok().json(print),
+        Ok(None) => {
+            let message = format!("Print with ID: {} not found", print_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = PrintModel),
+(status = 400, description = "Unknown material or printer", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Gcode not found or not downloadable by the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreatePrint, description="gcodeId and successful are required"),
+)]
+#[post("/prints")]
+pub async fn create_print(
+    user: AuthUser,
+    body: web::Json<CreatePrint>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    // only G-code the caller could have downloaded can have been printed by them
+    match select_downloadable_gcode_file(body.gcode_id, Some(user.id), &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let message = format!("Gcode with ID: {} not found", body.gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
+    let query_result = match insert_print(&body, user.id, &data).await {
+        Ok(id) => select_print(id, &data).await,
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(print)) => HttpResponse::Created().json(print),
+        Ok(None) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "Created print could not be read"})),
+        Err(e) => print_write_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintModel),
+(status = 400, description = "Unknown material or printer", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Print not found or not owned by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdatePrint, description="only supplied parameters are changed"),
+params(
+("id" = String, Path, description = "Print Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/prints/{id}")]
+pub async fn edit_print(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdatePrint>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let print_id = path.into_inner();
+    let query_result = match update_print(print_id, &body, user.id, &data).await {
+        Ok(true) => select_print(print_id, &data).await,
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(print)) => HttpResponse::Ok().json(print),
+        Ok(None) => {
+            let message = format!("Print with ID: {} not found", print_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => print_write_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Print not found or not owned by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Print Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/prints/{id}")]
+pub async fn remove_print(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let print_id = path.into_inner();
+    match delete_print(print_id, user.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => {
+            let message = format!("Print with ID: {} not found", print_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+fn print_write_error(e: sqlx::Error) -> HttpResponse {
+    if e.to_string().contains("violates foreign key constraint") {
+        return HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Referenced gcode, material or printer does not exist"}),
+        );
+    }
+    if e.to_string().contains("violates check constraint") {
+        return HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Successful prints cannot have a failure category"}),
+        );
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+/// Files the caller may not see get a 404, so private and hidden files are not revealed.
+pub async fn require_file_visible(
+    file_id: Uuid,
+    user: Option<AuthUser>,
+    data: &web::Data<AppState>,
+) -> Result<(), HttpResponse> {
+    match is_file_visible(file_id, user.map(|user| user.id), data).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            let message = format!("File with ID: {} not found", file_id);
+            Err(HttpResponse::NotFound().json(json!({"status": "fail","message": message})))
+        }
+        Err(e) => Err(HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)}))),
+    }
+}
+
+/// Prints are as visible as their file, others get a 404 so the print is not revealed.
+async fn require_print_visible(
+    print_id: Uuid,
+    user: Option<AuthUser>,
+    data: &web::Data<AppState>,
+) -> Result<(), HttpResponse> {
+    match is_print_visible(print_id, user.map(|user| user.id), data).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            let message = format!("Print with ID: {} not found", print_id);
+            Err(HttpResponse::NotFound().json(json!({"status": "fail","message": message})))
+        }
+        Err(e) => Err(HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)}))),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = PrintPhotoModel),
+(status = 400, description = "Unsupported content type or photo too large", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Print not found or not owned by caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = Vec<u8>, description = "JPEG, PNG or WebP image", content_type = "image/jpeg"),
+params(
+("id" = String, Path, description = "Print Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/prints/{id}/photos")]
+pub async fn upload_print_photo(
+    user: AuthUser,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let print_id = path.into_inner();
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !IMAGE_CONTENT_TYPES.contains(&content_type) {
+        let message = format!("Content-Type must be one of {}", IMAGE_CONTENT_TYPES.join(", "));
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    if body.is_empty() || body.len() > MAX_IMAGE_BYTES {
+        let message = format!("Photo must be between 1 and {} bytes", MAX_IMAGE_BYTES);
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+
+    match is_print_owner(print_id, user.id, &data).await {
+        Ok(true) => {}
+        Ok(false) => {
+            let message = format!("Print with ID: {} not found", print_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    }
+
+    let photo = match insert_print_photo(print_id, content_type, body.len() as i64, &data).await {
+        Ok(photo) => photo,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+    if let Err(e) = data.storage.write(photo.id, &body).await {
+        let _ = delete_print_photo(photo.id, &data).await;
+        return HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)}));
+    }
+    HttpResponse::Created().json(photo)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<PrintPhotoModel>),
+(status = 404, description = "Print not found, or its file is not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Print Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/prints/{id}/photos")]
+pub async fn get_print_photos(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let print_id = path.into_inner();
+    if let Err(response) = require_print_visible(print_id, user, &data).await {
+        return response;
+    }
+    match select_print_photos(print_id, &data).await {
+        Ok(photos) => HttpResponse::Ok().json(photos),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "Image content"),
+(status = 404, description = "Photo not found, or the file of its print is not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Photo Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/print-photos/{id}")]
+pub async fn get_print_photo_content(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let photo_id = path.into_inner();
+    let photo = match select_print_photo(photo_id, &data).await {
+        Ok(Some(photo)) => photo,
+        Ok(None) => {
+            let message = format!("Photo with ID: {} not found", photo_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+    if let Err(response) = require_print_visible(photo.print_id, user, &data).await {
+        return response;
+    }
+    match data.storage.read(photo.id).await {
+        Ok(content) => HttpResponse::Ok().content_type(photo.content_type).body(content),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "Failed prints per failure category", body = Vec<FailureReasonCount>),
+(status = 404, description = "File not found or not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/files/{id}/failure-reasons")]
+pub async fn get_failure_reasons(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_visible(file_id, user, &data).await {
+        return response;
+    }
+    match select_failure_reasons(file_id, &data).await {
+        Ok(reasons) => HttpResponse::Ok().json(reasons),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
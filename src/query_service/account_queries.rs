@@ -0,0 +1,210 @@
+use crate::{
+    model::{CollectionModel, FailureCategory, FileResponseModel, JobStatus, PrintJobModel, PrintModel, PrintPhotoModel},
+    query_service::file_queries::delete_files_with_dependents,
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+pub enum AccountDeletion {
+    /// Carries the storage blobs that are no longer referenced
+    Deleted(Vec<Uuid>),
+    NotFound,
+    UnknownRecipient,
+}
+
+pub async fn select_owned_files(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<FileResponseModel>, Error> {
+    sqlx::query_as!(
+        FileResponseModel,
+        "SELECT f.id, fullname, created, sizebytes, downloads, average_rating, is_downloadable, is_public,
+            description, license, version
+        FROM file f
+            JOIN files_per_user fpu ON fpu.files_pk = f.id
+        WHERE fpu.user_account_pk = $1 AND fpu.roles_pk = 'owner'
+        ORDER BY created",
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_prints_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintModel>, Error> {
+    sqlx::query_as!(
+        PrintModel,
+        r#"select pr.id as id, nozzle_size_mm, bed_temp_celsius, extruder_temp, successful,
+            concat(mb.full_name, ' ', m.description) as filament,
+            concat(mat_type, '') as filament_type, concat(pb.full_name, ' ', model) as printer,
+            pr.gcode_fk as gcode_id,
+            pr.material_fk as material_id, pr.printer_fk as printer_id, pr.user_account_fk as owner_id,
+            pr.failure_category as "failure_category: FailureCategory", pr.notes, pr.created,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) as filament_grams,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) / 1000 * m.price_per_kg
+                as filament_cost
+        from print pr
+            left join material m on m.id = pr.material_fk
+            left join printer p on p.id = pr.printer_fk
+            left join material_brand mb on mb.id = m.material_brand_fk
+            LEFT JOIN printer_brand pb on pb.id = p.printer_brand_fk
+            left join gcode g on g.id = pr.gcode_fk
+        where pr.user_account_fk = $1
+        ORDER BY pr.created"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_print_photos_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintPhotoModel>, Error> {
+    sqlx::query_as!(
+        PrintPhotoModel,
+        "SELECT pp.id, pp.print_fk as print_id, pp.content_type, pp.sizebytes, pp.created
+        FROM print_photo pp
+            JOIN print pr ON pr.id = pp.print_fk
+        WHERE pr.user_account_fk = $1
+        ORDER BY pp.created",
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_collections_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<CollectionModel>, Error> {
+    sqlx::query_as!(
+        CollectionModel,
+        r#"SELECT c.id, c.name, c.created,
+            COALESCE(array_agg(fpc.files_pk) FILTER (WHERE fpc.files_pk IS NOT NULL), '{}') as "file_ids!"
+        FROM collection c
+            LEFT JOIN files_per_collection fpc ON fpc.collection_pk = c.id
+        WHERE c.owner_user_pk = $1
+        GROUP BY c.id
+        ORDER BY c.created"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Jobs of all printers of the user, including finished ones.
+pub async fn select_jobs_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintJobModel>, Error> {
+    sqlx::query_as!(
+        PrintJobModel,
+        r#"SELECT pj.id, pj.user_printer_fk as user_printer_id, pj.gcode_fk as gcode_id,
+            pj.material_fk as material_id, pj.priority, pj.position, pj.status as "status: JobStatus",
+            pj.created, pj.started, pj.finished, pj.print_fk as print_id, pj.progress
+        FROM print_job pj
+            JOIN user_printer up ON up.id = pj.user_printer_fk
+        WHERE up.user_account_fk = $1
+        ORDER BY pj.created"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Deletes the account in one transaction.
+///
+/// Files only this user owns are handed to `recipient` as owner, or deleted with everything
+/// that depends on them; files of an organization stay with it. The user's prints of other
+/// files stay in the statistics without author, notes and photos. Mails, grants, sessions,
+/// collections and printers go with the account through cascading keys.
+pub async fn delete_account(
+    user_id: Uuid,
+    recipient: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<AccountDeletion, Error> {
+    let mut tx = data.db.begin().await?;
+    let avatar_id = match sqlx::query_scalar!(
+        "SELECT avatar_id FROM user_account WHERE id = $1 FOR UPDATE",
+        user_id
+    )
+        .fetch_optional(&mut tx)
+        .await?
+    {
+        Some(avatar_id) => avatar_id,
+        None => return Ok(AccountDeletion::NotFound),
+    };
+    if let Some(recipient) = recipient {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM user_account WHERE id = $1) as "exists!""#,
+            recipient
+        )
+            .fetch_one(&mut tx)
+            .await?;
+        if !exists || recipient == user_id {
+            return Ok(AccountDeletion::UnknownRecipient);
+        }
+    }
+
+    let sole_owned = sqlx::query_scalar!(
+        "SELECT files_pk FROM files_per_user fpu
+            JOIN file f ON f.id = fpu.files_pk
+        WHERE user_account_pk = $1 AND roles_pk = 'owner' AND f.organization_fk IS NULL
+            AND NOT EXISTS(SELECT 1 FROM files_per_user other
+                WHERE other.files_pk = fpu.files_pk AND other.roles_pk = 'owner'
+                    AND other.user_account_pk <> $1)",
+        user_id
+    )
+        .fetch_all(&mut tx)
+        .await?;
+
+    let mut blobs = Vec::new();
+    match recipient {
+        Some(recipient) => {
+            sqlx::query!(
+                "INSERT INTO files_per_user (user_account_pk, roles_pk, files_pk)
+                SELECT $1, 'owner', unnest($2::uuid[])
+                ON CONFLICT (user_account_pk, files_pk) DO UPDATE SET roles_pk = 'owner'",
+                recipient,
+                &sole_owned
+            )
+                .execute(&mut tx)
+                .await?;
+        }
+        None => blobs.extend(delete_files_with_dependents(&mut tx, &sole_owned).await?),
+    }
+
+    let photos = sqlx::query_scalar!(
+        "DELETE FROM print_photo WHERE print_fk IN (SELECT id FROM print WHERE user_account_fk = $1)
+        RETURNING id",
+        user_id
+    )
+        .fetch_all(&mut tx)
+        .await?;
+    blobs.extend(photos);
+    sqlx::query!(
+        "UPDATE print SET user_account_fk = NULL, notes = NULL WHERE user_account_fk = $1",
+        user_id
+    )
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM password_reset_request
+        WHERE mail IN (SELECT lower(mail) FROM user_account_mails WHERE user_account_pk = $1)",
+        user_id
+    )
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!("DELETE FROM user_account WHERE id = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+
+    blobs.extend(avatar_id);
+    Ok(AccountDeletion::Deleted(blobs))
+}
@@ -0,0 +1,146 @@
+use crate::{
+    model::{AbuseReportModel, UserRole},
+    query_service::file_queries::delete_files_with_dependents,
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+pub async fn update_user_role(
+    user_id: Uuid,
+    role: UserRole,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE user_account SET role = $1 WHERE id = $2",
+        role as UserRole,
+        user_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Suspends the account and ends its sessions. API keys are kept but rejected while the
+/// account is suspended.
+pub async fn suspend_user(
+    user_id: Uuid,
+    reason: Option<&str>,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let mut tx = data.db.begin().await?;
+    let rows_affected = sqlx::query!(
+        "UPDATE user_account SET suspended = COALESCE(suspended, now()), suspension_reason = $1
+         WHERE id = $2",
+        reason,
+        user_id
+    )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    sqlx::query!("DELETE FROM user_session WHERE user_account_fk = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(rows_affected > 0)
+}
+
+pub async fn unsuspend_user(user_id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE user_account SET suspended = NULL, suspension_reason = NULL WHERE id = $1",
+        user_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn set_file_hidden(
+    file_id: Uuid,
+    is_hidden: bool,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE file SET is_hidden = $1 WHERE id = $2",
+        is_hidden,
+        file_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Deletes any file regardless of its owners. Returns `None` if the file does not exist,
+/// otherwise the storage blobs to remove.
+pub async fn delete_any_file(
+    file_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<Vec<Uuid>>, Error> {
+    let mut tx = data.db.begin().await?;
+    let blobs = delete_files_with_dependents(&mut tx, &[file_id]).await?;
+    if !blobs.contains(&file_id) {
+        return Ok(None);
+    }
+    tx.commit().await?;
+    Ok(Some(blobs))
+}
+
+pub async fn insert_abuse_report(
+    file_id: Uuid,
+    reporter_id: Uuid,
+    reason: &str,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO abuse_report (file_fk, reporter_fk, reason) VALUES ($1, $2, $3) RETURNING id",
+        file_id,
+        reporter_id,
+        reason
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn select_abuse_reports(
+    resolved: bool,
+    limit: usize,
+    offset: usize,
+    data: &web::Data<AppState>
+) -> Result<Vec<AbuseReportModel>, Error> {
+    sqlx::query_as!(
+        AbuseReportModel,
+        "SELECT r.id, r.file_fk as file_id, f.fullname, f.is_hidden, r.reporter_fk as reporter_id,
+            r.reason, r.created, r.resolved, r.resolved_by_fk as resolved_by
+        FROM abuse_report r
+            JOIN file f ON f.id = r.file_fk
+        WHERE (r.resolved IS NOT NULL) = $1
+        ORDER BY r.created LIMIT $2 OFFSET $3",
+        resolved,
+        limit as i32,
+        offset as i32
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn resolve_abuse_report(
+    report_id: Uuid,
+    moderator_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE abuse_report SET resolved = COALESCE(resolved, now()),
+            resolved_by_fk = COALESCE(resolved_by_fk, $1)
+         WHERE id = $2",
+        moderator_id,
+        report_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
@@ -0,0 +1,267 @@
+use crate::{
+    model::{ApiKeyModel, ApiKeyScope},
+    schema::CreateApiKey,
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+const SESSION_TTL_DAYS: i32 = 30;
+pub const RESET_TOKEN_TTL_MINUTES: i32 = 60;
+/// Reset mails sent per address and hour.
+const RESET_REQUESTS_PER_HOUR: i64 = 3;
+
+pub enum PasswordReset {
+    Reset,
+    Expired,
+    Invalid,
+}
+
+pub struct Credentials {
+    pub user_id: Uuid,
+    pub password_hash: Option<String>,
+    pub suspended: bool,
+}
+
+/// Login is only possible with a verified mail.
+pub async fn select_credentials_by_mail(
+    mail: &str,
+    data: &web::Data<AppState>
+) -> Result<Option<Credentials>, Error> {
+    sqlx::query_as!(
+        Credentials,
+        r#"SELECT ua.id as user_id, ua.password_hash, ua.suspended IS NOT NULL as "suspended!"
+         FROM user_account ua
+            JOIN user_account_mails um ON um.user_account_pk = ua.id
+         WHERE lower(um.mail) = $1 AND um.is_verified"#,
+        mail
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// `None` if the user does not exist, otherwise the password hash if one is set.
+pub async fn select_password_hash(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<Option<String>>, Error> {
+    sqlx::query_scalar!("SELECT password_hash FROM user_account WHERE id = $1", user_id)
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Stores the new hash and ends every session except `keep_session`.
+pub async fn update_password(
+    user_id: Uuid,
+    password_hash: &str,
+    keep_session: Option<&str>,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    let mut tx = data.db.begin().await?;
+    sqlx::query!(
+        "UPDATE user_account SET password_hash = $1 WHERE id = $2",
+        password_hash,
+        user_id
+    )
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM user_session WHERE user_account_fk = $1 AND token_hash IS DISTINCT FROM $2",
+        user_id,
+        keep_session
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await
+}
+
+pub async fn insert_session(
+    user_id: Uuid,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<chrono::DateTime<chrono::Utc>, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO user_session (token_hash, user_account_fk, expires)
+         VALUES ($1, $2, now() + make_interval(days => $3))
+         RETURNING expires",
+        token_hash,
+        user_id,
+        SESSION_TTL_DAYS
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn delete_session(token_hash: &str, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM user_session WHERE token_hash = $1", token_hash)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Whether the session was created less than `minutes` ago, so its user has just logged in.
+pub async fn is_recent_session(
+    token_hash: &str,
+    minutes: i32,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM user_session WHERE token_hash = $1
+            AND created > now() - make_interval(mins => $2)) as "exists!""#,
+        token_hash,
+        minutes
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// Records a reset request for the mail unless it already had too many in the last hour.
+/// Returns `false` if the request is rate limited.
+pub async fn record_reset_request(mail: &str, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let mut tx = data.db.begin().await?;
+    // unknown mails have no row to lock, concurrent requests for a mail queue up on this lock
+    // until the transaction ends, so each one counts the requests recorded before it
+    sqlx::query!("SELECT pg_advisory_xact_lock(hashtext($1))", mail)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM password_reset_request WHERE mail = $1 AND requested < now() - interval '1 hour'",
+        mail
+    )
+        .execute(&mut tx)
+        .await?;
+    let inserted = sqlx::query!(
+        "INSERT INTO password_reset_request (mail)
+         SELECT $1::varchar WHERE (SELECT count(*) FROM password_reset_request WHERE mail = $1) < $2",
+        mail,
+        RESET_REQUESTS_PER_HOUR
+    )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    tx.commit().await?;
+    Ok(inserted > 0)
+}
+
+/// Creates a reset token for the owner of a verified mail, replacing earlier ones.
+/// Returns `false` if no account has this verified mail.
+pub async fn insert_reset_token(
+    mail: &str,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let mut tx = data.db.begin().await?;
+    let user_id = sqlx::query_scalar!(
+        "SELECT user_account_pk FROM user_account_mails WHERE lower(mail) = $1 AND is_verified",
+        mail
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let user_id = match user_id {
+        Some(user_id) => user_id,
+        None => return Ok(false),
+    };
+    sqlx::query!("DELETE FROM password_reset_token WHERE user_account_fk = $1", user_id)
+        .execute(&mut tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO password_reset_token (token_hash, user_account_fk, expires)
+         VALUES ($1, $2, now() + make_interval(mins => $3))",
+        token_hash,
+        user_id,
+        RESET_TOKEN_TTL_MINUTES
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Consumes the token and, if it is still valid, rotates the password hash and ends all
+/// sessions of the account.
+pub async fn reset_password(
+    token_hash: &str,
+    password_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<PasswordReset, Error> {
+    let mut tx = data.db.begin().await?;
+    let token = sqlx::query!(
+        r#"DELETE FROM password_reset_token WHERE token_hash = $1
+         RETURNING user_account_fk, expires > now() as "valid!""#,
+        token_hash
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let reset = match token {
+        None => PasswordReset::Invalid,
+        Some(token) if !token.valid => PasswordReset::Expired,
+        Some(token) => {
+            sqlx::query!(
+                "UPDATE user_account SET password_hash = $1 WHERE id = $2",
+                password_hash,
+                token.user_account_fk
+            )
+                .execute(&mut tx)
+                .await?;
+            sqlx::query!("DELETE FROM user_session WHERE user_account_fk = $1", token.user_account_fk)
+                .execute(&mut tx)
+                .await?;
+            PasswordReset::Reset
+        }
+    };
+    tx.commit().await?;
+    Ok(reset)
+}
+
+pub async fn insert_api_key(
+    user_id: Uuid,
+    key: &CreateApiKey,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<ApiKeyModel, Error> {
+    sqlx::query_as!(
+        ApiKeyModel,
+        r#"INSERT INTO api_key (user_account_fk, name, token_hash, scopes, expires)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id, name, scopes as "scopes: Vec<ApiKeyScope>", created, expires, last_used"#,
+        user_id,
+        key.name.trim(),
+        token_hash,
+        &key.scopes as &[ApiKeyScope],
+        key.expires
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn select_api_keys(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<ApiKeyModel>, Error> {
+    sqlx::query_as!(
+        ApiKeyModel,
+        r#"SELECT id, name, scopes as "scopes: Vec<ApiKeyScope>", created, expires, last_used
+         FROM api_key WHERE user_account_fk = $1 ORDER BY created"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn delete_api_key(
+    id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM api_key WHERE id = $1 AND user_account_fk = $2",
+        id,
+        user_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
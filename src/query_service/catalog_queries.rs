@@ -0,0 +1,293 @@
+use crate::{
+    model::{BrandModel, MaterialCatalogModel, MaterialType, PrinterCatalogModel},
+    schema::{CatalogSearchOptions, CreateMaterial, CreatePrinter, UpdateMaterial, UpdatePrinter},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+fn page(opts: &CatalogSearchOptions) -> (i64, i64) {
+    let limit = opts.limit.unwrap_or(10);
+    let offset = (opts.page.unwrap_or(1).max(1) - 1) * limit;
+    (limit as i64, offset as i64)
+}
+
+pub async fn select_printer_brands(
+    opts: &CatalogSearchOptions,
+    data: &web::Data<AppState>
+) -> Result<Vec<BrandModel>, Error> {
+    let (limit, offset) = page(opts);
+    sqlx::query_as!(
+        BrandModel,
+        "SELECT id, full_name FROM printer_brand
+         WHERE ($1::varchar IS NULL OR full_name ILIKE '%' || $1 || '%')
+         ORDER BY full_name LIMIT $2 OFFSET $3",
+        opts.q,
+        limit,
+        offset
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn insert_printer_brand(
+    full_name: &str,
+    data: &web::Data<AppState>
+) -> Result<BrandModel, Error> {
+    sqlx::query_as!(
+        BrandModel,
+        "INSERT INTO printer_brand (full_name) VALUES ($1) RETURNING id, full_name",
+        full_name
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn update_printer_brand(
+    id: Uuid,
+    full_name: &str,
+    data: &web::Data<AppState>
+) -> Result<Option<BrandModel>, Error> {
+    sqlx::query_as!(
+        BrandModel,
+        "UPDATE printer_brand SET full_name = $1 WHERE id = $2 RETURNING id, full_name",
+        full_name,
+        id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn delete_printer_brand(id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM printer_brand WHERE id = $1", id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_material_brands(
+    opts: &CatalogSearchOptions,
+    data: &web::Data<AppState>
+) -> Result<Vec<BrandModel>, Error> {
+    let (limit, offset) = page(opts);
+    sqlx::query_as!(
+        BrandModel,
+        "SELECT id, full_name FROM material_brand
+         WHERE ($1::varchar IS NULL OR full_name ILIKE '%' || $1 || '%')
+         ORDER BY full_name LIMIT $2 OFFSET $3",
+        opts.q,
+        limit,
+        offset
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn insert_material_brand(
+    full_name: &str,
+    data: &web::Data<AppState>
+) -> Result<BrandModel, Error> {
+    sqlx::query_as!(
+        BrandModel,
+        "INSERT INTO material_brand (full_name) VALUES ($1) RETURNING id, full_name",
+        full_name
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn update_material_brand(
+    id: Uuid,
+    full_name: &str,
+    data: &web::Data<AppState>
+) -> Result<Option<BrandModel>, Error> {
+    sqlx::query_as!(
+        BrandModel,
+        "UPDATE material_brand SET full_name = $1 WHERE id = $2 RETURNING id, full_name",
+        full_name,
+        id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn delete_material_brand(id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM material_brand WHERE id = $1", id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_printers(
+    opts: &CatalogSearchOptions,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrinterCatalogModel>, Error> {
+    let (limit, offset) = page(opts);
+    sqlx::query_as!(
+        PrinterCatalogModel,
+        "SELECT p.id, p.model, pb.id as brand_id, pb.full_name as brand FROM printer p
+            JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+         WHERE ($1::varchar IS NULL OR concat(pb.full_name, ' ', p.model) ILIKE '%' || $1 || '%')
+         AND ($2::uuid IS NULL OR pb.id = $2)
+         ORDER BY pb.full_name, p.model LIMIT $3 OFFSET $4",
+        opts.q,
+        opts.brand_id,
+        limit,
+        offset
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_printer(
+    id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<PrinterCatalogModel>, Error> {
+    sqlx::query_as!(
+        PrinterCatalogModel,
+        "SELECT p.id, p.model, pb.id as brand_id, pb.full_name as brand FROM printer p
+            JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+         WHERE p.id = $1",
+        id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn insert_printer(
+    printer: &CreatePrinter,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO printer (model, printer_brand_fk) VALUES ($1, $2) RETURNING id",
+        printer.model.trim(),
+        printer.brand_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn update_printer(
+    id: Uuid,
+    printer: &UpdatePrinter,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE printer SET model = COALESCE($1, model),
+            printer_brand_fk = COALESCE($2, printer_brand_fk)
+         WHERE id = $3",
+        printer.model.as_deref().map(str::trim),
+        printer.brand_id,
+        id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn delete_printer(id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM printer WHERE id = $1", id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_materials(
+    opts: &CatalogSearchOptions,
+    data: &web::Data<AppState>
+) -> Result<Vec<MaterialCatalogModel>, Error> {
+    let (limit, offset) = page(opts);
+    sqlx::query_as!(
+        MaterialCatalogModel,
+        r#"SELECT m.id, m.description, m.mat_type as "mat_type: MaterialType",
+            mb.id as brand_id, mb.full_name as brand, m.density_g_cm3, m.diameter_mm, m.price_per_kg
+         FROM material m
+            JOIN material_brand mb ON mb.id = m.material_brand_fk
+         WHERE ($1::varchar IS NULL OR concat(mb.full_name, ' ', m.description) ILIKE '%' || $1 || '%')
+         AND ($2::uuid IS NULL OR mb.id = $2)
+         AND ($3::material_type IS NULL OR m.mat_type = $3)
+         ORDER BY mb.full_name, m.description LIMIT $4 OFFSET $5"#,
+        opts.q,
+        opts.brand_id,
+        opts.mat_type as Option<MaterialType>,
+        limit,
+        offset
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_material(
+    id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<MaterialCatalogModel>, Error> {
+    sqlx::query_as!(
+        MaterialCatalogModel,
+        r#"SELECT m.id, m.description, m.mat_type as "mat_type: MaterialType",
+            mb.id as brand_id, mb.full_name as brand, m.density_g_cm3, m.diameter_mm, m.price_per_kg
+         FROM material m
+            JOIN material_brand mb ON mb.id = m.material_brand_fk
+         WHERE m.id = $1"#,
+        id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn insert_material(
+    material: &CreateMaterial,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO material (description, mat_type, material_brand_fk, density_g_cm3, diameter_mm, price_per_kg)
+         VALUES ($1, $2, $3, $4, COALESCE($5::float8, 1.75), $6) RETURNING id",
+        material.description.trim(),
+        material.mat_type as MaterialType,
+        material.brand_id,
+        material.density_g_cm3,
+        material.diameter_mm,
+        material.price_per_kg
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn update_material(
+    id: Uuid,
+    material: &UpdateMaterial,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE material SET description = COALESCE($1, description),
+            mat_type = COALESCE($2, mat_type),
+            material_brand_fk = COALESCE($3, material_brand_fk),
+            density_g_cm3 = COALESCE($4, density_g_cm3),
+            diameter_mm = COALESCE($5, diameter_mm),
+            price_per_kg = COALESCE($6, price_per_kg)
+         WHERE id = $7",
+        material.description.as_deref().map(str::trim),
+        material.mat_type as Option<MaterialType>,
+        material.brand_id,
+        material.density_g_cm3,
+        material.diameter_mm,
+        material.price_per_kg,
+        id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn delete_material(id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM material WHERE id = $1", id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
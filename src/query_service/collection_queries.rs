@@ -0,0 +1,55 @@
+use crate::{
+    schema::{CreateCollection, GetIdSchema},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+pub async fn insert_collection(
+    collection: &CreateCollection,
+    owner_id: Uuid,
+    data: web::Data<AppState>
+) -> Result<GetIdSchema, Error> {
+    let mut tx = data.db.begin().await?;
+    let created = sqlx::query_as!(
+        GetIdSchema,
+        "INSERT INTO collection (name, owner_user_pk) VALUES ($1, $2) RETURNING id",
+        collection.name,
+        owner_id
+    )
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO files_per_collection (collection_pk, files_pk)
+         SELECT $1, unnest($2::uuid[]) ON CONFLICT DO NOTHING",
+        created.id,
+        &collection.file_ids
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(created)
+}
+
+pub async fn select_collection_file_ids(
+    collection_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<Vec<Uuid>>, Error> {
+    let exists = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM collection WHERE id = $1) as "exists!""#,
+        collection_id
+    )
+        .fetch_one(&data.db)
+        .await?;
+    if !exists {
+        return Ok(None);
+    }
+    let ids = sqlx::query_scalar!(
+        "SELECT files_pk FROM files_per_collection WHERE collection_pk = $1",
+        collection_id
+    )
+        .fetch_all(&data.db)
+        .await?;
+    Ok(Some(ids))
+}
@@ -1,11 +1,11 @@
 use crate::{
-    model::FileResponseModel,
+    model::{BulkItemResult, DownloadableFile, FileResponseModel},
     FilePublicResponseModel, FilePrivateResponseModel,
-    schema::{CreateFile},
+    schema::{BulkUpdateFiles, CreateFile, ImportOptions, UpdateFile},
     AppState,
 };
 use actix_web::web;
-use sqlx::Error;
+use sqlx::{Connection, Error, PgConnection};
 use uuid::Uuid;
 
 pub async fn select_public(
@@ -16,11 +16,11 @@ pub async fn select_public(
     let query_result = sqlx::query_as!(
         FilePublicResponseModel,
         "select file.id as id, fullname, created, sizebytes, downloads, average_rating,
-        is_downloadable, ua.user_name as owner from file
+        is_downloadable, ua.user_name as owner, version from file
             left join files_per_user fpu on file.id = fpu.files_pk
             left join user_account ua on ua.id = fpu.user_account_pk
          where fpu.roles_pk = 'owner'
-         and file.is_public LIMIT $1 OFFSET $2",
+         and file.is_public and not file.is_hidden LIMIT $1 OFFSET $2",
         limit as i32,
         offset as i32
     )
@@ -35,25 +35,28 @@ pub async fn select_private(
     offset: usize,
     data: web::Data<AppState>
 ) -> Result<Vec<FilePrivateResponseModel>, Error> {
+    // files owned by an organization are listed under its name, access comes from
+    // direct and group grants as well as the caller's organization role
     let query_result = sqlx::query_as!(FilePrivateResponseModel,
-        "SELECT q1.user_name as owner, q1.file_id as id,
+        r#"SELECT q1.owner as "owner!", q1.file_id as id,
             CASE WHEN q2.roles_pk IN ('owner', 'download') THEN true ELSE false END as is_downloadable,
             q1.fullname as fullname, q1.created as created, q1.sizebytes as sizebytes,
-            q1.downloads as downloads, q1.average_rating as average_rating
+            q1.downloads as downloads, q1.average_rating as average_rating, q1.version as "version!"
         FROM (
-            SELECT user_name, file.id AS file_id,fullname, created, sizebytes, downloads,average_rating
+            SELECT COALESCE(o.name, ua.user_name, '') AS owner, file.id AS file_id, file.fullname, file.created,
+                file.sizebytes, file.downloads, file.average_rating, file.version
             FROM file
-                LEFT JOIN files_per_user fpu ON file.id = fpu.files_pk
+                LEFT JOIN files_per_user fpu ON file.id = fpu.files_pk AND fpu.roles_pk = 'owner'
                 LEFT JOIN user_account ua ON ua.id = fpu.user_account_pk
-            WHERE roles_pk = 'owner' AND file.is_public = false
+                LEFT JOIN organization o ON o.id = file.organization_fk
+            WHERE file.is_public = false AND (fpu.user_account_pk IS NOT NULL OR o.id IS NOT NULL)
         ) AS q1
             JOIN (
         SELECT roles_pk, files_pk AS file_id
-        FROM files_per_user fpu
-            LEFT JOIN user_account ua ON ua.id = fpu.user_account_pk
-        WHERE fpu.user_account_pk = $1
+        FROM effective_file_role
+        WHERE user_account_pk = $1
         )
-        AS q2 ON q1.file_id = q2.file_id LIMIT $2 OFFSET $3",
+        AS q2 ON q1.file_id = q2.file_id LIMIT $2 OFFSET $3"#,
         id,
         limit as i32,
         offset as i32
@@ -65,6 +68,7 @@ pub async fn select_private(
 
 pub async fn insert_file(
     file: web::Json<CreateFile>,
+    owner_id: Uuid,
     data: web::Data<AppState>
 ) -> Result<FileResponseModel, Error> {
     let query_result = sqlx::query_as!(
@@ -73,14 +77,16 @@ pub async fn insert_file(
             WITH inserted_file AS (
                 INSERT INTO file (fullname, downloads, average_rating, sizebytes, is_downloadable, is_public)
                     VALUES ($1, $2, $3, $4, $5, $6)
-                    RETURNING id, fullname, created, sizebytes, downloads, average_rating, is_downloadable, is_public
+                    RETURNING id, fullname, created, sizebytes, downloads, average_rating, is_downloadable, is_public,
+                        description, license, version
             ), inserted_files_per_user AS (
                 INSERT INTO files_per_user (user_account_pk, roles_pk, files_pk)
                     VALUES ($7, 'owner', (SELECT id FROM inserted_file))
                     RETURNING user_account_pk, roles_pk, files_pk
             )
             SELECT inserted_file.id, inserted_file.fullname, inserted_file.created, inserted_file.sizebytes,
-            inserted_file.downloads, inserted_file.average_rating, inserted_file.is_downloadable, inserted_file.is_public
+            inserted_file.downloads, inserted_file.average_rating, inserted_file.is_downloadable, inserted_file.is_public,
+            inserted_file.description, inserted_file.license, inserted_file.version
             FROM inserted_file
         ",
         file.fullname,
@@ -89,9 +95,354 @@ pub async fn insert_file(
         file.sizebytes,
         file.is_downloadable,
         file.is_public,
-        Uuid::parse_str(&file.owner_user_id).unwrap_or(Uuid::new_v4())
+        owner_id
     )
         .fetch_one(&data.db)
         .await;
     query_result
-}
\ No newline at end of file
+}
+
+/// Updates only the supplied fields in a single statement and bumps the version. Returns `None`
+/// if the file does not exist or its version is not one of `expected_versions`.
+pub async fn update_file(
+    id: Uuid,
+    file: &UpdateFile,
+    expected_versions: Option<Vec<i32>>,
+    data: &web::Data<AppState>
+) -> Result<Option<FileResponseModel>, Error> {
+    sqlx::query_as!(
+        FileResponseModel,
+        "UPDATE file SET fullname = COALESCE($1, fullname),
+            is_public = COALESCE($2, is_public),
+            is_downloadable = COALESCE($3, is_downloadable),
+            description = COALESCE($4, description),
+            license = COALESCE($5, license),
+            version = version + 1
+         WHERE id = $6 AND ($7::int[] IS NULL OR version = ANY($7))
+         RETURNING id, fullname, created, sizebytes, downloads, average_rating, is_downloadable, is_public,
+            description, license, version",
+        file.fullname,
+        file.is_public,
+        file.is_downloadable,
+        file.description,
+        file.license,
+        id,
+        expected_versions.as_deref()
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Returns `false` if the file does not exist or its version is not one of `expected_versions`.
+/// Deletes the file with its dependents if its version is one of `expected_versions`.
+/// Returns the storage blobs to remove, `None` if the file is missing or was changed.
+pub async fn delete_file_versioned(
+    id: Uuid,
+    expected_versions: Option<Vec<i32>>,
+    data: &web::Data<AppState>
+) -> Result<Option<Vec<Uuid>>, Error> {
+    let mut tx = data.db.begin().await?;
+    let current = sqlx::query_scalar!(
+        "SELECT id FROM file WHERE id = $1 AND ($2::int[] IS NULL OR version = ANY($2)) FOR UPDATE",
+        id,
+        expected_versions.as_deref()
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    if current.is_none() {
+        return Ok(None);
+    }
+    let blobs = delete_files_with_dependents(&mut tx, &[id]).await?;
+    tx.commit().await?;
+    Ok(Some(blobs))
+}
+
+pub async fn file_exists(id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM file WHERE id = $1) as "exists!""#,
+        id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// The strongest role `user_id` has on the file, directly or through an organization.
+pub async fn select_file_role(
+    file_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<String>, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT roles_pk as "roles_pk!" FROM effective_file_role WHERE files_pk = $1 AND user_account_pk = $2"#,
+        file_id,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Returns `None` if the file does not exist, otherwise whether `user_id` owns it.
+async fn owns_file(
+    conn: &mut PgConnection,
+    file_id: Uuid,
+    user_id: Uuid
+) -> Result<Option<bool>, Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS(SELECT 1 FROM file WHERE id = $1) as "exists!",
+            EXISTS(SELECT 1 FROM effective_file_role
+                WHERE files_pk = $1 AND user_account_pk = $2 AND roles_pk = 'owner') as "is_owner!""#,
+        file_id,
+        user_id
+    )
+        .fetch_one(conn)
+        .await?;
+    Ok(row.exists.then_some(row.is_owner))
+}
+
+pub fn normalize_tags(tags: &Option<Vec<String>>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .iter()
+        .flatten()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+async fn update_one(
+    conn: &mut PgConnection,
+    file_id: Uuid,
+    body: &BulkUpdateFiles,
+    add_tags: &[String],
+    remove_tags: &[String]
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE file SET is_public = COALESCE($1, is_public),
+            is_downloadable = COALESCE($2, is_downloadable),
+            version = version + 1
+         WHERE id = $3",
+        body.is_public,
+        body.is_downloadable,
+        file_id
+    )
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO files_per_tag (files_pk, tag) SELECT $1, unnest($2::varchar[])
+         ON CONFLICT DO NOTHING",
+        file_id,
+        add_tags
+    )
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM files_per_tag WHERE files_pk = $1 AND tag = ANY($2)",
+        file_id,
+        remove_tags
+    )
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Deletes the files together with their G-code, the prints and print jobs of that G-code
+/// and the photos of those prints. Returns the storage blobs that are no longer referenced.
+pub async fn delete_files_with_dependents(
+    conn: &mut PgConnection,
+    ids: &[Uuid]
+) -> Result<Vec<Uuid>, Error> {
+    let mut blobs = sqlx::query_scalar!(
+        "DELETE FROM print_photo WHERE print_fk IN (
+            SELECT pr.id FROM print pr JOIN gcode g ON g.id = pr.gcode_fk WHERE g.file_pk = ANY($1))
+        RETURNING id",
+        ids
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM print_job WHERE gcode_fk IN (SELECT id FROM gcode WHERE file_pk = ANY($1))",
+        ids
+    )
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "DELETE FROM print WHERE gcode_fk IN (SELECT id FROM gcode WHERE file_pk = ANY($1))",
+        ids
+    )
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM gcode WHERE file_pk = ANY($1)", ids)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!("DELETE FROM files_per_user WHERE files_pk = ANY($1)", ids)
+        .execute(&mut *conn)
+        .await?;
+    let files = sqlx::query_scalar!("DELETE FROM file WHERE id = ANY($1) RETURNING id", ids)
+        .fetch_all(&mut *conn)
+        .await?;
+    blobs.extend(files);
+    Ok(blobs)
+}
+
+/// Applies the update to every file in one transaction. Each file runs in its own
+/// savepoint, so a missing, foreign or failing file is reported without undoing the rest.
+pub async fn bulk_update(
+    user_id: Uuid,
+    body: &BulkUpdateFiles,
+    data: web::Data<AppState>
+) -> Result<Vec<BulkItemResult>, Error> {
+    let add_tags = normalize_tags(&body.add_tags);
+    let remove_tags = normalize_tags(&body.remove_tags);
+    let mut tx = data.db.begin().await?;
+    let mut results = Vec::with_capacity(body.ids.len());
+
+    for &file_id in &body.ids {
+        match owns_file(&mut tx, file_id, user_id).await? {
+            None => results.push(BulkItemResult::fail(file_id, "File not found")),
+            Some(false) => results.push(BulkItemResult::fail(file_id, "Not owner of file")),
+            Some(true) => {
+                let mut savepoint = tx.begin().await?;
+                match update_one(&mut savepoint, file_id, body, &add_tags, &remove_tags).await {
+                    Ok(()) => {
+                        savepoint.commit().await?;
+                        results.push(BulkItemResult::ok(file_id));
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await?;
+                        results.push(BulkItemResult::error(file_id, format!("{:?}", e)));
+                    }
+                }
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok(results)
+}
+
+/// Like `bulk_update`, with each file deleted together with its dependents. Also returns the
+/// storage blobs of the deleted files, to be removed once the deletion is committed.
+pub async fn bulk_delete(
+    user_id: Uuid,
+    ids: &[Uuid],
+    data: web::Data<AppState>
+) -> Result<(Vec<BulkItemResult>, Vec<Uuid>), Error> {
+    let mut tx = data.db.begin().await?;
+    let mut results = Vec::with_capacity(ids.len());
+    let mut blobs = Vec::new();
+
+    for &file_id in ids {
+        match owns_file(&mut tx, file_id, user_id).await? {
+            None => results.push(BulkItemResult::fail(file_id, "File not found")),
+            Some(false) => results.push(BulkItemResult::fail(file_id, "Not owner of file")),
+            Some(true) => {
+                let mut savepoint = tx.begin().await?;
+                match delete_files_with_dependents(&mut savepoint, &[file_id]).await {
+                    Ok(deleted) => {
+                        savepoint.commit().await?;
+                        blobs.extend(deleted);
+                        results.push(BulkItemResult::ok(file_id));
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await?;
+                        results.push(BulkItemResult::error(file_id, format!("{:?}", e)));
+                    }
+                }
+            }
+        }
+    }
+
+    tx.commit().await?;
+    Ok((results, blobs))
+}
+
+/// Keeps only the files `user_id` may download.
+pub async fn select_downloadable_files(
+    ids: &[Uuid],
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<Vec<DownloadableFile>, Error> {
+    sqlx::query_as!(
+        DownloadableFile,
+        "SELECT id, fullname FROM file
+         WHERE id = ANY($1) AND (
+            (is_public AND is_downloadable AND NOT is_hidden)
+            OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = file.id AND fpu.user_account_pk = $2
+                AND fpu.roles_pk IN ('owner', 'download')))",
+        ids,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn count_downloads(ids: &[Uuid], data: &web::Data<AppState>) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE file SET downloads = COALESCE(downloads, 0) + 1 WHERE id = ANY($1)",
+        ids
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+pub struct ImportedFile {
+    pub id: Uuid,
+    pub gcode_id: Option<Uuid>,
+}
+
+/// Creates the `file` row of one imported bundle entry together with its owner, tags and,
+/// for G-code entries, the matching `gcode` row. The caller decides whether to commit.
+pub async fn insert_imported_file(
+    conn: &mut PgConnection,
+    fullname: &str,
+    sizebytes: i64,
+    owner_id: Uuid,
+    options: &ImportOptions,
+    tags: &[String],
+    is_gcode: bool
+) -> Result<ImportedFile, Error> {
+    let id = sqlx::query_scalar!(
+        "INSERT INTO file (fullname, downloads, average_rating, sizebytes, is_downloadable, is_public)
+            VALUES ($1, 0, 0, $2, $3, $4)
+            RETURNING id",
+        fullname,
+        sizebytes,
+        options.is_downloadable.unwrap_or(true),
+        options.is_public.unwrap_or(true)
+    )
+        .fetch_one(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO files_per_user (user_account_pk, roles_pk, files_pk) VALUES ($1, 'owner', $2)",
+        owner_id,
+        id
+    )
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO files_per_tag (files_pk, tag) SELECT $1, unnest($2::varchar[])
+         ON CONFLICT DO NOTHING",
+        id,
+        tags
+    )
+        .execute(&mut *conn)
+        .await?;
+
+    let gcode_id = if is_gcode {
+        let gcode_id = sqlx::query_scalar!(
+            "INSERT INTO gcode (file_pk) VALUES ($1) RETURNING id",
+            id
+        )
+            .fetch_one(&mut *conn)
+            .await?;
+        Some(gcode_id)
+    } else {
+        None
+    };
+
+    Ok(ImportedFile { id, gcode_id })
+}
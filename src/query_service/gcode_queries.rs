@@ -0,0 +1,191 @@
+use crate::{
+    model::{DownloadableFile, FilamentUsage, GcodeVariantModel},
+    schema::LinkGcodeVariant,
+    AppState,
+};
+use actix_web::web;
+use sqlx::{Error, PgConnection};
+use uuid::Uuid;
+
+/// The stored file of a G-code, if `user_id` may download it.
+pub async fn select_downloadable_gcode_file(
+    gcode_id: Uuid,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<Option<DownloadableFile>, Error> {
+    sqlx::query_as!(
+        DownloadableFile,
+        "SELECT f.id, f.fullname FROM gcode g
+            JOIN file f ON f.id = g.file_pk
+         WHERE g.id = $1 AND (
+            (f.is_public AND f.is_downloadable AND NOT f.is_hidden)
+            OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $2
+                AND fpu.roles_pk IN ('owner', 'download')))",
+        gcode_id,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub struct GcodeFilament {
+    pub file_id: Uuid,
+    pub filament_length_mm: Option<f64>,
+}
+
+/// The extracted filament length of a G-code, if `user_id` may see its file.
+pub async fn select_gcode_filament(
+    gcode_id: Uuid,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<Option<GcodeFilament>, Error> {
+    sqlx::query_as!(
+        GcodeFilament,
+        "SELECT f.id as file_id, g.filament_length_mm FROM gcode g
+            JOIN file f ON f.id = g.file_pk
+         WHERE g.id = $1 AND ((f.is_public AND NOT f.is_hidden)
+            OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $2))",
+        gcode_id,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn update_filament_length(
+    conn: &mut PgConnection,
+    gcode_id: Uuid,
+    length_mm: f64
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE gcode SET filament_length_mm = $1 WHERE id = $2",
+        length_mm,
+        gcode_id
+    )
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Grams and cost of `length_mm` of the given material, `None` if the material does not exist.
+pub async fn select_material_usage(
+    material_id: Uuid,
+    length_mm: Option<f64>,
+    data: &web::Data<AppState>
+) -> Result<Option<FilamentUsage>, Error> {
+    let row = sqlx::query!(
+        "SELECT filament_grams($1, diameter_mm, density_g_cm3) as grams,
+            filament_grams($1, diameter_mm, density_g_cm3) / 1000 * price_per_kg as cost
+         FROM material WHERE id = $2",
+        length_mm,
+        material_id
+    )
+        .fetch_optional(&data.db)
+        .await?;
+    Ok(row.map(|row| FilamentUsage { length_mm, grams: row.grams, cost: row.cost }))
+}
+
+/// Whether the file exists and is public or shared with `user_id`.
+pub async fn is_file_visible(
+    file_id: Uuid,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM file f WHERE f.id = $1 AND ((f.is_public AND NOT f.is_hidden)
+            OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $2))) as "exists!""#,
+        file_id,
+        user_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// Variants of a model file visible to `user_id`, the most successful first. `gcode_id`
+/// narrows the result down to a single variant.
+pub async fn select_gcode_variants(
+    model_file_id: Uuid,
+    gcode_id: Option<Uuid>,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<Vec<GcodeVariantModel>, Error> {
+    sqlx::query_as!(
+        GcodeVariantModel,
+        r#"SELECT g.id as gcode_id, f.id as file_id, f.fullname, g.label,
+            g.printer_fk as printer_id, pb.full_name || ' ' || p.model as printer,
+            g.material_fk as material_id, mb.full_name || ' ' || m.description as material,
+            (f.is_public AND f.is_downloadable AND NOT f.is_hidden) OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $3
+                AND fpu.roles_pk IN ('owner', 'download')) as "is_downloadable!",
+            g.filament_length_mm,
+            count(pr.id) as "prints!",
+            count(pr.id) FILTER (WHERE pr.successful) as "successful_prints!",
+            avg(pr.successful::int)::float8 as success_rate
+        FROM gcode g
+            JOIN file f ON f.id = g.file_pk
+            LEFT JOIN printer p ON p.id = g.printer_fk
+            LEFT JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+            LEFT JOIN material m ON m.id = g.material_fk
+            LEFT JOIN material_brand mb ON mb.id = m.material_brand_fk
+            LEFT JOIN print pr ON pr.gcode_fk = g.id
+        WHERE g.model_file_fk = $1
+        AND ($2::uuid IS NULL OR g.id = $2)
+        AND ((f.is_public AND NOT f.is_hidden) OR EXISTS(SELECT 1 FROM effective_file_role fpu
+            WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $3))
+        GROUP BY g.id, f.id, p.id, pb.id, m.id, mb.id
+        ORDER BY success_rate DESC NULLS LAST, count(pr.id) DESC, f.fullname"#,
+        model_file_id,
+        gcode_id,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Links a G-code owned by `owner_id` to the model it was sliced from. Returns `false` if
+/// no such G-code is owned by `owner_id`.
+pub async fn link_gcode_variant(
+    model_file_id: Uuid,
+    variant: &LinkGcodeVariant,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE gcode SET model_file_fk = $1, printer_fk = $2, material_fk = $3, label = $4
+         WHERE id = $5 AND EXISTS(SELECT 1 FROM effective_file_role fpu
+            WHERE fpu.files_pk = gcode.file_pk AND fpu.user_account_pk = $6 AND fpu.roles_pk = 'owner')",
+        model_file_id,
+        variant.printer_id,
+        variant.material_id,
+        variant.label.as_deref().map(str::trim).filter(|label| !label.is_empty()),
+        variant.gcode_id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn unlink_gcode_variant(
+    model_file_id: Uuid,
+    gcode_id: Uuid,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE gcode SET model_file_fk = NULL
+         WHERE id = $1 AND model_file_fk = $2 AND EXISTS(SELECT 1 FROM effective_file_role fpu
+            WHERE fpu.files_pk = gcode.file_pk AND fpu.user_account_pk = $3 AND fpu.roles_pk = 'owner')",
+        gcode_id,
+        model_file_id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
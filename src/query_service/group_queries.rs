@@ -0,0 +1,183 @@
+use crate::{
+    model::{FileGroupGrantModel, GroupModel, UserSearchResult},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+/// Creates the group with its owner as first member.
+pub async fn insert_group(
+    name: &str,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    let mut tx = data.db.begin().await?;
+    let id = sqlx::query_scalar!(
+        "INSERT INTO user_group (name, owner_user_fk) VALUES ($1, $2) RETURNING id",
+        name,
+        owner_id
+    )
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO user_group_member (group_fk, user_account_fk) VALUES ($1, $2)",
+        id,
+        owner_id
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Groups the user owns or belongs to.
+pub async fn select_groups_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<GroupModel>, Error> {
+    sqlx::query_as!(
+        GroupModel,
+        r#"SELECT g.id, g.name, g.owner_user_fk as owner_id, g.created,
+            (SELECT count(*) FROM user_group_member m WHERE m.group_fk = g.id) as "members!"
+        FROM user_group g
+        WHERE g.owner_user_fk = $1
+            OR EXISTS(SELECT 1 FROM user_group_member m WHERE m.group_fk = g.id AND m.user_account_fk = $1)
+        ORDER BY g.name"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub struct GroupAccess {
+    pub is_owner: bool,
+    pub is_member: bool,
+}
+
+/// `None` if the group does not exist.
+pub async fn select_group_access(
+    group_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<GroupAccess>, Error> {
+    sqlx::query_as!(
+        GroupAccess,
+        r#"SELECT g.owner_user_fk = $2 as "is_owner!",
+            EXISTS(SELECT 1 FROM user_group_member m WHERE m.group_fk = g.id AND m.user_account_fk = $2)
+                as "is_member!"
+        FROM user_group g WHERE g.id = $1"#,
+        group_id,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn delete_group(group_id: Uuid, data: &web::Data<AppState>) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM user_group WHERE id = $1", group_id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_group_members(
+    group_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserSearchResult>, Error> {
+    sqlx::query_as!(
+        UserSearchResult,
+        r#"SELECT ua.id, COALESCE(ua.display_name, ua.user_name) as "display_name!"
+        FROM user_group_member m
+            JOIN user_account ua ON ua.id = m.user_account_fk
+        WHERE m.group_fk = $1
+        ORDER BY 2, ua.id"#,
+        group_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn insert_group_member(
+    group_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO user_group_member (group_fk, user_account_fk) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        group_id,
+        user_id
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_group_member(
+    group_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM user_group_member WHERE group_fk = $1 AND user_account_fk = $2",
+        group_id,
+        user_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_file_group_grants(
+    file_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<FileGroupGrantModel>, Error> {
+    sqlx::query_as!(
+        FileGroupGrantModel,
+        "SELECT g.id as group_id, g.name, fpg.roles_pk as role
+        FROM files_per_group fpg
+            JOIN user_group g ON g.id = fpg.group_fk
+        WHERE fpg.files_pk = $1
+        ORDER BY g.name",
+        file_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Grants the role or replaces the group's previous one.
+pub async fn upsert_file_group_grant(
+    file_id: Uuid,
+    group_id: Uuid,
+    role: &str,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!(
+        "INSERT INTO files_per_group (group_fk, roles_pk, files_pk) VALUES ($1, $2, $3)
+         ON CONFLICT (group_fk, files_pk) DO UPDATE SET roles_pk = excluded.roles_pk",
+        group_id,
+        role,
+        file_id
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_file_group_grant(
+    file_id: Uuid,
+    group_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM files_per_group WHERE files_pk = $1 AND group_fk = $2",
+        file_id,
+        group_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
@@ -1 +1,14 @@
 pub mod file_queries;
+pub mod collection_queries;
+pub mod print_queries;
+pub mod catalog_queries;
+pub mod stats_queries;
+pub mod queue_queries;
+pub mod gcode_queries;
+pub mod user_queries;
+pub mod auth_queries;
+pub mod account_queries;
+pub mod admin_queries;
+pub mod organization_queries;
+pub mod group_queries;
+pub mod oidc_queries;
\ No newline at end of file
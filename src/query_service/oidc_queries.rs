@@ -0,0 +1,182 @@
+use crate::{
+    model::UserIdentityModel,
+    query_service::user_queries::release_mail_claims,
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+/// Time to log in at the provider and be redirected back.
+const LOGIN_STATE_TTL_MINUTES: i32 = 10;
+
+pub struct LoginState {
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+pub enum IdentityLogin {
+    User { user_id: Uuid, suspended: bool },
+    /// First login without a mail the provider has verified, so there is nothing to link or
+    /// register the account with
+    MailRequired,
+}
+
+/// Stores a started login and drops the ones nobody finished.
+pub async fn insert_login_state(
+    state_hash: &str,
+    nonce: &str,
+    code_verifier: &str,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM oidc_login_state WHERE expires < now()")
+        .execute(&data.db)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO oidc_login_state (state_hash, nonce, code_verifier, expires)
+         VALUES ($1, $2, $3, now() + make_interval(mins => $4))",
+        state_hash,
+        nonce,
+        code_verifier,
+        LOGIN_STATE_TTL_MINUTES
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+/// Each state can be used once, `None` if it is unknown, used or expired.
+pub async fn take_login_state(
+    state_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<Option<LoginState>, Error> {
+    sqlx::query_as!(
+        LoginState,
+        "DELETE FROM oidc_login_state WHERE state_hash = $1 AND expires > now()
+         RETURNING nonce, code_verifier",
+        state_hash
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Finds the account of the identity. On first login it is linked to the account with the
+/// verified `mail`, or a new account is created with `mail` as verified primary mail.
+pub async fn login_identity(
+    issuer: &str,
+    subject: &str,
+    mail: Option<&str>,
+    user_name: &str,
+    display_name: Option<&str>,
+    data: &web::Data<AppState>
+) -> Result<IdentityLogin, Error> {
+    // concurrent first logins of one identity race to link it, the loser starts over and
+    // finds the account the winner linked
+    match try_login_identity(issuer, subject, mail, user_name, display_name, data).await? {
+        Some(login) => Ok(login),
+        None => try_login_identity(issuer, subject, mail, user_name, display_name, data)
+            .await?
+            .ok_or(Error::RowNotFound),
+    }
+}
+
+/// `None` if a concurrent login linked the identity or registered the mail first, nothing
+/// is changed then.
+async fn try_login_identity(
+    issuer: &str,
+    subject: &str,
+    mail: Option<&str>,
+    user_name: &str,
+    display_name: Option<&str>,
+    data: &web::Data<AppState>
+) -> Result<Option<IdentityLogin>, Error> {
+    let mut tx = data.db.begin().await?;
+    let known = sqlx::query!(
+        r#"UPDATE user_identity ui SET last_login = now()
+         FROM user_account ua
+         WHERE ua.id = ui.user_account_fk AND ui.issuer = $1 AND ui.subject = $2
+         RETURNING ua.id, ua.suspended IS NOT NULL as "suspended!""#,
+        issuer,
+        subject
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    if let Some(known) = known {
+        tx.commit().await?;
+        return Ok(Some(IdentityLogin::User { user_id: known.id, suspended: known.suspended }));
+    }
+
+    let mail = match mail {
+        Some(mail) => mail,
+        None => return Ok(Some(IdentityLogin::MailRequired)),
+    };
+    // the provider verified the mail, so it replaces claims nobody verified
+    release_mail_claims(&mut tx, mail).await?;
+    let registered = sqlx::query!(
+        r#"SELECT ua.id, ua.suspended IS NOT NULL as "suspended!"
+         FROM user_account_mails um
+            JOIN user_account ua ON ua.id = um.user_account_pk
+         WHERE lower(um.mail) = $1"#,
+        mail
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let (user_id, suspended) = match registered {
+        Some(registered) => (registered.id, registered.suspended),
+        None => {
+            let id = sqlx::query_scalar!(
+                "INSERT INTO user_account (user_name, display_name) VALUES ($1, $2) RETURNING id",
+                user_name,
+                display_name
+            )
+                .fetch_one(&mut tx)
+                .await?;
+            let inserted = sqlx::query!(
+                "INSERT INTO user_account_mails (mail, user_account_pk, is_primary, is_verified)
+                 VALUES ($1, $2, true, true)
+                 ON CONFLICT DO NOTHING",
+                mail,
+                id
+            )
+                .execute(&mut tx)
+                .await?
+                .rows_affected();
+            if inserted == 0 {
+                tx.rollback().await?;
+                return Ok(None);
+            }
+            (id, false)
+        }
+    };
+    let inserted = sqlx::query!(
+        "INSERT INTO user_identity (issuer, subject, user_account_fk, last_login) VALUES ($1, $2, $3, now())
+         ON CONFLICT DO NOTHING",
+        issuer,
+        subject,
+        user_id
+    )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    if inserted == 0 {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+    tx.commit().await?;
+    Ok(Some(IdentityLogin::User { user_id, suspended }))
+}
+
+pub async fn select_identities_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserIdentityModel>, Error> {
+    sqlx::query_as!(
+        UserIdentityModel,
+        "SELECT issuer, subject, created, last_login FROM user_identity
+         WHERE user_account_fk = $1
+         ORDER BY created",
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
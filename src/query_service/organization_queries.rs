@@ -0,0 +1,197 @@
+use crate::{
+    model::{OrganizationMemberModel, OrganizationModel, OrganizationRole},
+    AppState,
+};
+use actix_web::web;
+use sqlx::{Error, PgConnection};
+use uuid::Uuid;
+
+pub enum MemberUpdate {
+    Updated,
+    NotFound,
+    /// The change would leave the organization without an admin
+    LastAdmin,
+}
+
+/// Creates the organization with `user_id` as its first admin.
+pub async fn insert_organization(
+    name: &str,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    let mut tx = data.db.begin().await?;
+    let id = sqlx::query_scalar!("INSERT INTO organization (name) VALUES ($1) RETURNING id", name)
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO organization_member (organization_fk, user_account_fk, role) VALUES ($1, $2, 'admin')",
+        id,
+        user_id
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+pub async fn select_organizations_of_user(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<OrganizationModel>, Error> {
+    sqlx::query_as!(
+        OrganizationModel,
+        r#"SELECT o.id, o.name, o.created, om.role as "role: OrganizationRole"
+        FROM organization o
+            JOIN organization_member om ON om.organization_fk = o.id
+        WHERE om.user_account_fk = $1
+        ORDER BY o.name"#,
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_organization_role(
+    organization_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<OrganizationRole>, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT role as "role: OrganizationRole" FROM organization_member
+         WHERE organization_fk = $1 AND user_account_fk = $2"#,
+        organization_id,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn select_members(
+    organization_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<OrganizationMemberModel>, Error> {
+    sqlx::query_as!(
+        OrganizationMemberModel,
+        r#"SELECT ua.id as user_id, ua.user_name, ua.display_name, om.role as "role: OrganizationRole",
+            om.created
+        FROM organization_member om
+            JOIN user_account ua ON ua.id = om.user_account_fk
+        WHERE om.organization_fk = $1
+        ORDER BY ua.user_name, ua.id"#,
+        organization_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+async fn has_admin(conn: &mut PgConnection, organization_id: Uuid) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM organization_member
+            WHERE organization_fk = $1 AND role = 'admin') as "exists!""#,
+        organization_id
+    )
+        .fetch_one(conn)
+        .await
+}
+
+/// Adds the user or changes their role.
+pub async fn upsert_member(
+    organization_id: Uuid,
+    user_id: Uuid,
+    role: OrganizationRole,
+    data: &web::Data<AppState>
+) -> Result<MemberUpdate, Error> {
+    let mut tx = data.db.begin().await?;
+    sqlx::query!(
+        "INSERT INTO organization_member (organization_fk, user_account_fk, role) VALUES ($1, $2, $3)
+         ON CONFLICT (organization_fk, user_account_fk) DO UPDATE SET role = excluded.role",
+        organization_id,
+        user_id,
+        role as OrganizationRole
+    )
+        .execute(&mut tx)
+        .await?;
+    if !has_admin(&mut tx, organization_id).await? {
+        return Ok(MemberUpdate::LastAdmin);
+    }
+    tx.commit().await?;
+    Ok(MemberUpdate::Updated)
+}
+
+pub async fn delete_member(
+    organization_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<MemberUpdate, Error> {
+    let mut tx = data.db.begin().await?;
+    let rows_affected = sqlx::query!(
+        "DELETE FROM organization_member WHERE organization_fk = $1 AND user_account_fk = $2",
+        organization_id,
+        user_id
+    )
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    if rows_affected == 0 {
+        return Ok(MemberUpdate::NotFound);
+    }
+    if !has_admin(&mut tx, organization_id).await? {
+        return Ok(MemberUpdate::LastAdmin);
+    }
+    tx.commit().await?;
+    Ok(MemberUpdate::Updated)
+}
+
+/// Fails with a foreign key violation while the organization still owns files.
+pub async fn delete_organization(
+    organization_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!("DELETE FROM organization WHERE id = $1", organization_id)
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn assign_file_organization(
+    file_id: Uuid,
+    organization_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE file SET organization_fk = $1 WHERE id = $2",
+        organization_id,
+        file_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Takes the file out of its organization. If nobody owns it directly any more, e.g. because
+/// the uploader deleted their account, `user_id` becomes its owner.
+pub async fn remove_file_organization(
+    file_id: Uuid,
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let mut tx = data.db.begin().await?;
+    let rows_affected = sqlx::query!("UPDATE file SET organization_fk = NULL WHERE id = $1", file_id)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    sqlx::query!(
+        "INSERT INTO files_per_user (user_account_pk, roles_pk, files_pk)
+         SELECT $1, 'owner', $2
+         WHERE NOT EXISTS(SELECT 1 FROM files_per_user WHERE files_pk = $2 AND roles_pk = 'owner')
+         ON CONFLICT (user_account_pk, files_pk) DO UPDATE SET roles_pk = 'owner'",
+        user_id,
+        file_id
+    )
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(rows_affected > 0)
+}
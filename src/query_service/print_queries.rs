@@ -0,0 +1,250 @@
+use crate::{
+    model::{FailureCategory, FailureReasonCount, PrintModel, PrintPhotoModel},
+    schema::{CreatePrint, UpdatePrint},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+pub async fn select_prints_for_file(
+    file_id: Uuid,
+    limit: usize,
+    offset: usize,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintModel>, Error> {
+    sqlx::query_as!(
+        PrintModel,
+        r#"select pr.id as id, nozzle_size_mm, bed_temp_celsius, extruder_temp, successful,
+            concat(mb.full_name, ' ', m.description) as filament,
+            concat(mat_type, '') as filament_type, concat(pb.full_name, ' ', model) as printer, g.id as gcode_id,
+            pr.material_fk as material_id, pr.printer_fk as printer_id, pr.user_account_fk as owner_id,
+            pr.failure_category as "failure_category: FailureCategory", pr.notes, pr.created,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) as filament_grams,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) / 1000 * m.price_per_kg
+                as filament_cost
+        from print pr
+            left join material m on m.id = pr.material_fk
+            left join printer p on p.id = pr.printer_fk
+            left join gcode g on g.id = pr.gcode_fk
+            left join material_brand mb on mb.id = m.material_brand_fk
+            LEFT JOIN printer_brand pb on pb.id = p.printer_brand_fk
+            left join file f on f.id = g.file_pk
+        where f.id = $1
+        ORDER by id LIMIT $2 OFFSET $3"#,
+        file_id,
+        limit as i32,
+        offset as i32,
+        )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_print(
+    id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<PrintModel>, Error> {
+    sqlx::query_as!(
+        PrintModel,
+        r#"select pr.id as id, nozzle_size_mm, bed_temp_celsius, extruder_temp, successful,
+            concat(mb.full_name, ' ', m.description) as filament,
+            concat(mat_type, '') as filament_type, concat(pb.full_name, ' ', model) as printer,
+            pr.gcode_fk as gcode_id,
+            pr.material_fk as material_id, pr.printer_fk as printer_id, pr.user_account_fk as owner_id,
+            pr.failure_category as "failure_category: FailureCategory", pr.notes, pr.created,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) as filament_grams,
+            filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) / 1000 * m.price_per_kg
+                as filament_cost
+        from print pr
+            left join material m on m.id = pr.material_fk
+            left join printer p on p.id = pr.printer_fk
+            left join material_brand mb on mb.id = m.material_brand_fk
+            LEFT JOIN printer_brand pb on pb.id = p.printer_brand_fk
+            left join gcode g on g.id = pr.gcode_fk
+        where pr.id = $1"#,
+        id
+        )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn insert_print(
+    print: &CreatePrint,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO print (gcode_fk, material_fk, printer_fk, nozzle_size_mm, bed_temp_celsius,
+            extruder_temp, successful, user_account_fk, failure_category, notes)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         RETURNING id",
+        print.gcode_id,
+        print.material_id,
+        print.printer_id,
+        print.nozzle_size_mm,
+        print.bed_temp_celsius,
+        print.extruder_temp,
+        print.successful,
+        owner_id,
+        print.failure_category as Option<FailureCategory>,
+        print.notes
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// Returns `false` if no print with that id is owned by `owner_id`.
+pub async fn update_print(
+    id: Uuid,
+    print: &UpdatePrint,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE print SET material_fk = COALESCE($1, material_fk),
+            printer_fk = COALESCE($2, printer_fk),
+            nozzle_size_mm = COALESCE($3, nozzle_size_mm),
+            bed_temp_celsius = COALESCE($4, bed_temp_celsius),
+            extruder_temp = COALESCE($5, extruder_temp),
+            successful = COALESCE($6, successful),
+            failure_category = CASE WHEN COALESCE($6, successful) THEN NULL
+                ELSE COALESCE($7, failure_category) END,
+            notes = COALESCE($8, notes)
+         WHERE id = $9 AND user_account_fk = $10",
+        print.material_id,
+        print.printer_id,
+        print.nozzle_size_mm,
+        print.bed_temp_celsius,
+        print.extruder_temp,
+        print.successful,
+        print.failure_category as Option<FailureCategory>,
+        print.notes,
+        id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Returns `false` if no print with that id is owned by `owner_id`.
+pub async fn delete_print(
+    id: Uuid,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM print WHERE id = $1 AND user_account_fk = $2",
+        id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn is_print_owner(
+    id: Uuid,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM print WHERE id = $1 AND user_account_fk = $2) as "exists!""#,
+        id,
+        owner_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// Whether the print exists and its file is public or shared with `user_id`.
+pub async fn is_print_visible(
+    id: Uuid,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM print pr
+            JOIN gcode g ON g.id = pr.gcode_fk
+            JOIN file f ON f.id = g.file_pk
+         WHERE pr.id = $1 AND ((f.is_public AND NOT f.is_hidden)
+            OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $2))) as "exists!""#,
+        id,
+        user_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn insert_print_photo(
+    print_id: Uuid,
+    content_type: &str,
+    sizebytes: i64,
+    data: &web::Data<AppState>
+) -> Result<PrintPhotoModel, Error> {
+    sqlx::query_as!(
+        PrintPhotoModel,
+        "INSERT INTO print_photo (print_fk, content_type, sizebytes) VALUES ($1, $2, $3)
+         RETURNING id, print_fk as print_id, content_type, sizebytes, created",
+        print_id,
+        content_type,
+        sizebytes
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn delete_print_photo(id: Uuid, data: &web::Data<AppState>) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM print_photo WHERE id = $1", id)
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+pub async fn select_print_photos(
+    print_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintPhotoModel>, Error> {
+    sqlx::query_as!(
+        PrintPhotoModel,
+        "SELECT id, print_fk as print_id, content_type, sizebytes, created FROM print_photo
+         WHERE print_fk = $1 ORDER BY created",
+        print_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_print_photo(
+    id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<PrintPhotoModel>, Error> {
+    sqlx::query_as!(
+        PrintPhotoModel,
+        "SELECT id, print_fk as print_id, content_type, sizebytes, created FROM print_photo WHERE id = $1",
+        id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn select_failure_reasons(
+    file_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<FailureReasonCount>, Error> {
+    sqlx::query_as!(
+        FailureReasonCount,
+        r#"SELECT pr.failure_category as "category: FailureCategory", count(*) as "prints!"
+        FROM print pr
+            JOIN gcode g ON g.id = pr.gcode_fk
+        WHERE g.file_pk = $1 AND NOT pr.successful
+        GROUP BY pr.failure_category
+        ORDER BY count(*) DESC"#,
+        file_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
@@ -0,0 +1,375 @@
+use crate::{
+    model::{ConnectorKind, FailureCategory, JobStatus, PrintJobModel, UserPrinterModel},
+    schema::{ConnectorSettings, CreatePrintJob, CreateUserPrinter, UpdatePrintJobStatus},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+pub enum JobUpdate {
+    Updated(PrintJobModel),
+    NotFound,
+    InvalidTransition(JobStatus),
+}
+
+pub async fn insert_user_printer(
+    printer: &CreateUserPrinter,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    sqlx::query_scalar!(
+        "INSERT INTO user_printer (name, printer_fk, user_account_fk) VALUES ($1, $2, $3) RETURNING id",
+        printer.name.trim(),
+        printer.printer_id,
+        owner_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn select_user_printers(
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserPrinterModel>, Error> {
+    sqlx::query_as!(
+        UserPrinterModel,
+        r#"SELECT up.id, up.name, up.printer_fk as printer_id,
+            concat(pb.full_name, ' ', p.model) as "printer!", up.created,
+            up.connector_kind as "connector_kind: ConnectorKind", up.connector_url
+        FROM user_printer up
+            JOIN printer p ON p.id = up.printer_fk
+            JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+        WHERE up.user_account_fk = $1
+        ORDER BY up.name"#,
+        owner_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_user_printer(
+    id: Uuid,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<UserPrinterModel>, Error> {
+    sqlx::query_as!(
+        UserPrinterModel,
+        r#"SELECT up.id, up.name, up.printer_fk as printer_id,
+            concat(pb.full_name, ' ', p.model) as "printer!", up.created,
+            up.connector_kind as "connector_kind: ConnectorKind", up.connector_url
+        FROM user_printer up
+            JOIN printer p ON p.id = up.printer_fk
+            JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+        WHERE up.id = $1 AND up.user_account_fk = $2"#,
+        id,
+        owner_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn delete_user_printer(
+    id: Uuid,
+    owner_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "DELETE FROM user_printer WHERE id = $1 AND user_account_fk = $2",
+        id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+/// Running and queued jobs first, in the order they will be printed: higher priority first,
+/// then by position within the same priority.
+pub async fn select_jobs(
+    user_printer_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintJobModel>, Error> {
+    sqlx::query_as!(
+        PrintJobModel,
+        r#"SELECT id, user_printer_fk as user_printer_id, gcode_fk as gcode_id, material_fk as material_id,
+            priority, position, status as "status: JobStatus", created, started, finished, print_fk as print_id, progress
+        FROM print_job
+        WHERE user_printer_fk = $1
+        ORDER BY CASE status WHEN 'printing' THEN 0 WHEN 'queued' THEN 1 ELSE 2 END,
+            priority DESC, position, created"#,
+        user_printer_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_next_job(
+    user_printer_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<PrintJobModel>, Error> {
+    sqlx::query_as!(
+        PrintJobModel,
+        r#"SELECT id, user_printer_fk as user_printer_id, gcode_fk as gcode_id, material_fk as material_id,
+            priority, position, status as "status: JobStatus", created, started, finished, print_fk as print_id, progress
+        FROM print_job
+        WHERE user_printer_fk = $1 AND status = 'queued'
+        ORDER BY priority DESC, position, created
+        LIMIT 1"#,
+        user_printer_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn insert_job(
+    user_printer_id: Uuid,
+    job: &CreatePrintJob,
+    data: &web::Data<AppState>
+) -> Result<PrintJobModel, Error> {
+    sqlx::query_as!(
+        PrintJobModel,
+        r#"INSERT INTO print_job (user_printer_fk, gcode_fk, material_fk, priority, position)
+            VALUES ($1, $2, $3, $4, (SELECT COALESCE(max(position), 0) + 1 FROM print_job
+                WHERE user_printer_fk = $1 AND status = 'queued'))
+        RETURNING id, user_printer_fk as user_printer_id, gcode_fk as gcode_id, material_fk as material_id,
+            priority, position, status as "status: JobStatus", created, started, finished, print_fk as print_id, progress"#,
+        user_printer_id,
+        job.gcode_id,
+        job.material_id,
+        job.priority.unwrap_or(0)
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn reorder_jobs(
+    user_printer_id: Uuid,
+    job_ids: &[Uuid],
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE print_job j SET position = o.ord::int
+        FROM unnest($1::uuid[]) WITH ORDINALITY AS o(id, ord)
+        WHERE j.id = o.id AND j.user_printer_fk = $2 AND j.status = 'queued'",
+        job_ids,
+        user_printer_id
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+/// Moves a job to `update.status`. When it finishes as done or failed, the outcome is recorded
+/// as a `print` row owned by the printer owner in the same transaction. `owner_id` restricts
+/// the update to jobs on that user's printers, `None` is used by the printer connectors.
+pub async fn update_job_status(
+    job_id: Uuid,
+    owner_id: Option<Uuid>,
+    update: &UpdatePrintJobStatus,
+    data: &web::Data<AppState>
+) -> Result<JobUpdate, Error> {
+    let mut tx = data.db.begin().await?;
+    let job = sqlx::query!(
+        r#"SELECT j.status as "status: JobStatus", j.gcode_fk, j.material_fk,
+            up.printer_fk, up.user_account_fk
+        FROM print_job j
+            JOIN user_printer up ON up.id = j.user_printer_fk
+        WHERE j.id = $1 AND ($2::uuid IS NULL OR up.user_account_fk = $2)
+        FOR UPDATE OF j"#,
+        job_id,
+        owner_id
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let job = match job {
+        Some(job) => job,
+        None => return Ok(JobUpdate::NotFound),
+    };
+    if !job.status.can_transition_to(update.status) {
+        return Ok(JobUpdate::InvalidTransition(job.status));
+    }
+
+    let print_id = if update.status.is_finished_print() {
+        let successful = update.status == JobStatus::Done;
+        let print_id = sqlx::query_scalar!(
+            "INSERT INTO print (gcode_fk, material_fk, printer_fk, nozzle_size_mm, bed_temp_celsius,
+                extruder_temp, successful, user_account_fk, failure_category, notes)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+             RETURNING id",
+            job.gcode_fk,
+            job.material_fk,
+            job.printer_fk,
+            update.nozzle_size_mm,
+            update.bed_temp_celsius,
+            update.extruder_temp,
+            successful,
+            job.user_account_fk,
+            update.failure_category.filter(|_| !successful) as Option<FailureCategory>,
+            update.notes
+        )
+            .fetch_one(&mut tx)
+            .await?;
+        Some(print_id)
+    } else {
+        None
+    };
+
+    let job = sqlx::query_as!(
+        PrintJobModel,
+        r#"UPDATE print_job SET status = $1,
+            started = CASE WHEN $1 = 'printing'::job_status THEN NOW() ELSE started END,
+            finished = CASE WHEN $1 <> 'printing'::job_status THEN NOW() ELSE finished END,
+            print_fk = COALESCE($2, print_fk)
+        WHERE id = $3
+        RETURNING id, user_printer_fk as user_printer_id, gcode_fk as gcode_id, material_fk as material_id,
+            priority, position, status as "status: JobStatus", created, started, finished, print_fk as print_id, progress"#,
+        update.status as JobStatus,
+        print_id,
+        job_id
+    )
+        .fetch_one(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(JobUpdate::Updated(job))
+}
+
+pub struct ConnectorConfig {
+    pub kind: ConnectorKind,
+    pub url: String,
+    pub api_key: Option<String>,
+}
+
+pub struct PrintingJob {
+    pub job_id: Uuid,
+    /// Whether the host has reported the job printing, which stores its progress
+    pub started: bool,
+    pub config: ConnectorConfig,
+}
+
+/// Returns `false` if no such printer is owned by `owner_id`.
+pub async fn update_connector(
+    user_printer_id: Uuid,
+    owner_id: Uuid,
+    settings: &ConnectorSettings,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE user_printer SET connector_kind = $1, connector_url = $2, connector_api_key = $3
+         WHERE id = $4 AND user_account_fk = $5",
+        settings.kind as Option<ConnectorKind>,
+        settings.url.as_deref().map(|url| url.trim_end_matches('/')),
+        settings.api_key,
+        user_printer_id,
+        owner_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_connector(
+    user_printer_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<ConnectorConfig>, Error> {
+    let row = sqlx::query!(
+        r#"SELECT connector_kind as "connector_kind: ConnectorKind", connector_url, connector_api_key
+        FROM user_printer WHERE id = $1"#,
+        user_printer_id
+    )
+        .fetch_optional(&data.db)
+        .await?;
+    Ok(row.and_then(|row| match (row.connector_kind, row.connector_url) {
+        (Some(kind), Some(url)) => Some(ConnectorConfig { kind, url, api_key: row.connector_api_key }),
+        _ => None,
+    }))
+}
+
+/// Jobs currently printing on a printer with a configured connector.
+pub async fn select_connected_printing_jobs(
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintingJob>, Error> {
+    let rows = sqlx::query!(
+        r#"SELECT j.id, j.progress IS NOT NULL as "started!", up.connector_kind as "connector_kind!: ConnectorKind",
+            up.connector_url as "connector_url!", up.connector_api_key
+        FROM print_job j
+            JOIN user_printer up ON up.id = j.user_printer_fk
+        WHERE j.status = 'printing'
+        AND up.connector_kind IS NOT NULL AND up.connector_url IS NOT NULL"#
+    )
+        .fetch_all(&data.db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| PrintingJob {
+            job_id: row.id,
+            started: row.started,
+            config: ConnectorConfig {
+                kind: row.connector_kind,
+                url: row.connector_url,
+                api_key: row.connector_api_key,
+            },
+        })
+        .collect())
+}
+
+/// Moves a queued job to printing while holding the printer row, so concurrent starts cannot
+/// both find the printer idle. `None` if the printer is already printing or the job is no
+/// longer queued.
+pub async fn start_job(
+    user_printer_id: Uuid,
+    job_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<PrintJobModel>, Error> {
+    let mut tx = data.db.begin().await?;
+    sqlx::query!("SELECT id FROM user_printer WHERE id = $1 FOR UPDATE", user_printer_id)
+        .fetch_optional(&mut tx)
+        .await?;
+    let job = sqlx::query_as!(
+        PrintJobModel,
+        r#"UPDATE print_job SET status = 'printing', started = NOW(), progress = NULL
+        WHERE id = $1 AND user_printer_fk = $2 AND status = 'queued'
+        AND NOT EXISTS(SELECT 1 FROM print_job WHERE user_printer_fk = $2 AND status = 'printing')
+        RETURNING id, user_printer_fk as user_printer_id, gcode_fk as gcode_id, material_fk as material_id,
+            priority, position, status as "status: JobStatus", created, started, finished, print_fk as print_id, progress"#,
+        job_id,
+        user_printer_id
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(job)
+}
+
+/// Puts a job started by `start_job` back in the queue when its upload failed.
+pub async fn requeue_job(
+    job_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE print_job SET status = 'queued', started = NULL, progress = NULL
+         WHERE id = $1 AND status = 'printing'",
+        job_id
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
+
+pub async fn update_job_progress(
+    job_id: Uuid,
+    progress: Option<f32>,
+    data: &web::Data<AppState>
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE print_job SET progress = $1 WHERE id = $2 AND status = 'printing'",
+        progress,
+        job_id
+    )
+        .execute(&data.db)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::{
+    model::{FilePrintCountModel, MonthlyFilamentUsage, PrintSettingsRow, PrintStatsModel},
+    AppState,
+};
+use actix_web::web;
+use sqlx::Error;
+use uuid::Uuid;
+
+/// Aggregates all prints matching every given filter, counting only prints of files visible
+/// to `user_id`. Temperature and nozzle ranges only consider successful prints, since those
+/// are the settings known to work.
+pub async fn select_print_stats(
+    file_id: Option<Uuid>,
+    printer_id: Option<Uuid>,
+    material_id: Option<Uuid>,
+    user_id: Option<Uuid>,
+    data: &web::Data<AppState>
+) -> Result<PrintStatsModel, Error> {
+    sqlx::query_as!(
+        PrintStatsModel,
+        r#"WITH prints AS (
+            SELECT pr.* FROM print pr
+                JOIN gcode g ON g.id = pr.gcode_fk
+                JOIN file f ON f.id = g.file_pk
+            WHERE ($1::uuid IS NULL OR g.file_pk = $1)
+            AND ($2::uuid IS NULL OR pr.printer_fk = $2)
+            AND ($3::uuid IS NULL OR pr.material_fk = $3)
+            AND ((f.is_public AND NOT f.is_hidden)
+                OR EXISTS(SELECT 1 FROM effective_file_role fpu
+                    WHERE fpu.files_pk = f.id AND fpu.user_account_pk = $4))
+        ), top_printer AS (
+            SELECT printer_fk AS id FROM prints WHERE printer_fk IS NOT NULL
+            GROUP BY printer_fk ORDER BY count(*) DESC, printer_fk LIMIT 1
+        ), top_material AS (
+            SELECT material_fk AS id FROM prints WHERE material_fk IS NOT NULL
+            GROUP BY material_fk ORDER BY count(*) DESC, material_fk LIMIT 1
+        )
+        SELECT
+            (SELECT count(*) FROM prints) as "prints!",
+            (SELECT count(*) FROM prints WHERE successful) as "successful_prints!",
+            (SELECT avg(successful::int)::float8 FROM prints) as success_rate,
+            (SELECT id FROM top_printer) as most_used_printer_id,
+            (SELECT concat(pb.full_name, ' ', p.model) FROM printer p
+                JOIN printer_brand pb ON pb.id = p.printer_brand_fk
+                WHERE p.id = (SELECT id FROM top_printer)) as most_used_printer,
+            (SELECT id FROM top_material) as most_used_material_id,
+            (SELECT concat(mb.full_name, ' ', m.description) FROM material m
+                JOIN material_brand mb ON mb.id = m.material_brand_fk
+                WHERE m.id = (SELECT id FROM top_material)) as most_used_material,
+            min(bed_temp_celsius) as min_bed_temp_celsius,
+            max(bed_temp_celsius) as max_bed_temp_celsius,
+            min(extruder_temp) as min_extruder_temp,
+            max(extruder_temp) as max_extruder_temp,
+            min(nozzle_size_mm) as min_nozzle_size_mm,
+            max(nozzle_size_mm) as max_nozzle_size_mm
+        FROM prints WHERE successful"#,
+        file_id,
+        printer_id,
+        material_id,
+        user_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+/// Public files ordered by the number of recorded prints.
+pub async fn select_most_printed(
+    limit: usize,
+    offset: usize,
+    data: &web::Data<AppState>
+) -> Result<Vec<FilePrintCountModel>, Error> {
+    sqlx::query_as!(
+        FilePrintCountModel,
+        r#"SELECT f.id, f.fullname,
+            count(pr.id) as "prints!",
+            count(pr.id) FILTER (WHERE pr.successful) as "successful_prints!",
+            avg(pr.successful::int)::float8 as success_rate
+        FROM file f
+            JOIN gcode g ON g.file_pk = f.id
+            JOIN print pr ON pr.gcode_fk = g.id
+        WHERE f.is_public AND NOT f.is_hidden
+        GROUP BY f.id, f.fullname
+        ORDER BY count(pr.id) DESC, f.fullname
+        LIMIT $1 OFFSET $2"#,
+        limit as i32,
+        offset as i32
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_successful_settings(
+    file_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<PrintSettingsRow>, Error> {
+    sqlx::query_as!(
+        PrintSettingsRow,
+        "SELECT pr.printer_fk as printer_id, pr.material_fk as material_id,
+            pr.nozzle_size_mm, pr.bed_temp_celsius, pr.extruder_temp
+        FROM print pr
+            JOIN gcode g ON g.id = pr.gcode_fk
+        WHERE g.file_pk = $1 AND pr.successful",
+        file_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Filament used by the prints of `user_id` per month, oldest first, covering the current
+/// month and the `months - 1` before it. Months without prints are left out.
+pub async fn select_monthly_filament_usage(
+    user_id: Uuid,
+    months: i32,
+    data: &web::Data<AppState>
+) -> Result<Vec<MonthlyFilamentUsage>, Error> {
+    sqlx::query_as!(
+        MonthlyFilamentUsage,
+        r#"WITH usage AS (
+            SELECT date_trunc('month', pr.created) AS month,
+                filament_grams(g.filament_length_mm, m.diameter_mm, m.density_g_cm3) AS grams,
+                m.price_per_kg
+            FROM print pr
+                JOIN gcode g ON g.id = pr.gcode_fk
+                LEFT JOIN material m ON m.id = pr.material_fk
+            WHERE pr.user_account_fk = $1
+            AND pr.created >= date_trunc('month', now()) - make_interval(months => $2 - 1)
+        )
+        SELECT month::date as "month!", count(*) as "prints!",
+            sum(grams) as grams, sum(grams / 1000 * price_per_kg) as cost,
+            count(*) FILTER (WHERE grams IS NULL) as "unknown_prints!"
+        FROM usage
+        GROUP BY month
+        ORDER BY month"#,
+        user_id,
+        months
+    )
+        .fetch_all(&data.db)
+        .await
+}
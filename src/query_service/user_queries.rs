@@ -0,0 +1,460 @@
+use crate::{
+    model::{FilePublicResponseModel, UserMailModel, UserModel, UserProfileModel, UserRole, UserSearchResult},
+    schema::UpdateProfile,
+    AppState,
+};
+use actix_web::web;
+use sqlx::{Error, PgConnection};
+use uuid::Uuid;
+
+/// How long a mail verification link stays valid.
+pub const VERIFICATION_TTL_HOURS: i32 = 24;
+
+pub enum MailUpdate {
+    Updated,
+    NotFound,
+    /// The mail is primary and cannot be removed
+    Primary,
+    /// Only verified mails can become primary
+    Unverified,
+}
+
+pub enum Verification {
+    Verified(String),
+    Expired,
+    Invalid,
+}
+
+/// Trims and lowercases a mail address, `None` if it cannot be one.
+pub fn normalize_mail(mail: &str) -> Option<String> {
+    let mail = mail.trim().to_lowercase();
+    let (local, domain) = mail.split_once('@')?;
+    let valid = !local.is_empty()
+        && domain.contains('.')
+        && !domain.starts_with('.')
+        && !domain.ends_with('.')
+        && !mail.chars().any(char::is_whitespace)
+        && mail.len() <= 100;
+    valid.then_some(mail)
+}
+
+/// Drops unverified claims on `mail` together with every claim whose verification link
+/// expired, so an address nobody verified can be registered again. Registrations left
+/// without any mail could never log in and are removed as well.
+pub async fn release_mail_claims(conn: &mut PgConnection, mail: &str) -> Result<(), Error> {
+    let released = sqlx::query_scalar!(
+        "DELETE FROM user_account_mails um
+         WHERE NOT um.is_verified AND (lower(um.mail) = $1
+            OR NOT EXISTS(SELECT 1 FROM mail_verification_token t
+                WHERE t.mail = um.mail AND t.expires > now()))
+         RETURNING um.user_account_pk",
+        mail
+    )
+        .fetch_all(&mut *conn)
+        .await?;
+    if released.is_empty() {
+        return Ok(());
+    }
+    sqlx::query!(
+        "DELETE FROM user_account ua WHERE ua.id = ANY($1)
+         AND NOT EXISTS(SELECT 1 FROM user_account_mails um WHERE um.user_account_pk = ua.id)",
+        &released
+    )
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Creates the account with an unverified primary mail and its verification token.
+pub async fn insert_user(
+    user_name: &str,
+    mail: &str,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<Uuid, Error> {
+    let mut tx = data.db.begin().await?;
+    release_mail_claims(&mut tx, mail).await?;
+    let id = sqlx::query_scalar!(
+        "INSERT INTO user_account (user_name) VALUES ($1) RETURNING id",
+        user_name
+    )
+        .fetch_one(&mut tx)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO user_account_mails (mail, user_account_pk, is_primary) VALUES ($1, $2, true)",
+        mail,
+        id
+    )
+        .execute(&mut tx)
+        .await?;
+    insert_verification_token(&mut tx, mail, token_hash).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Resolves the caller's own mails, and for admins every verified mail, so nobody can be
+/// found by an address they do not control.
+pub async fn select_user_id_by_mail(
+    mail: &str,
+    requester_id: Uuid,
+    is_admin: bool,
+    data: &web::Data<AppState>
+) -> Result<Option<Uuid>, Error> {
+    sqlx::query_scalar!(
+        "SELECT user_account_pk FROM user_account_mails
+         WHERE lower(mail) = $1 AND (user_account_pk = $2 OR ($3 AND is_verified))",
+        mail,
+        requester_id,
+        is_admin
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+pub async fn select_users(
+    limit: usize,
+    offset: usize,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserModel>, Error> {
+    sqlx::query_as!(
+        UserModel,
+        r#"SELECT ua.id, ua.user_name, ua.display_name, um.mail as "primary_mail?",
+            ua.role as "role: UserRole", ua.suspended, ua.suspension_reason
+        FROM user_account ua
+            LEFT JOIN user_account_mails um ON um.user_account_pk = ua.id AND um.is_primary
+        ORDER BY ua.user_name, ua.id LIMIT $1 OFFSET $2"#,
+        limit as i32,
+        offset as i32
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub const MIN_SEARCH_LENGTH: usize = 2;
+const SEARCH_LIMIT: i64 = 20;
+
+/// Matches display and user names, never mails.
+pub async fn search_users(
+    query: &str,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserSearchResult>, Error> {
+    sqlx::query_as!(
+        UserSearchResult,
+        r#"SELECT id, COALESCE(display_name, user_name) as "display_name!"
+        FROM user_account
+        WHERE display_name ILIKE '%' || $1 || '%' OR user_name ILIKE '%' || $1 || '%'
+        ORDER BY COALESCE(display_name, user_name), id
+        LIMIT $2"#,
+        query,
+        SEARCH_LIMIT
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_mails(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Vec<UserMailModel>, Error> {
+    sqlx::query_as!(
+        UserMailModel,
+        "SELECT mail, is_verified, is_primary, created FROM user_account_mails
+         WHERE user_account_pk = $1
+         ORDER BY is_primary DESC, mail",
+        user_id
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+/// Adds an unverified, non-primary mail together with its verification token.
+pub async fn insert_mail(
+    user_id: Uuid,
+    mail: &str,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<UserMailModel, Error> {
+    let mut tx = data.db.begin().await?;
+    release_mail_claims(&mut tx, mail).await?;
+    let mail = sqlx::query_as!(
+        UserMailModel,
+        "INSERT INTO user_account_mails (mail, user_account_pk) VALUES ($1, $2)
+         RETURNING mail, is_verified, is_primary, created",
+        mail,
+        user_id
+    )
+        .fetch_one(&mut tx)
+        .await?;
+    insert_verification_token(&mut tx, &mail.mail, token_hash).await?;
+    tx.commit().await?;
+    Ok(mail)
+}
+
+/// Replaces the pending verification token of an unverified mail owned by `user_id`.
+/// Returns `false` if there is no such mail.
+pub async fn renew_verification_token(
+    user_id: Uuid,
+    mail: &str,
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let mut tx = data.db.begin().await?;
+    let pending = sqlx::query_scalar!(
+        r#"SELECT EXISTS(SELECT 1 FROM user_account_mails
+            WHERE mail = $1 AND user_account_pk = $2 AND NOT is_verified) as "exists!""#,
+        mail,
+        user_id
+    )
+        .fetch_one(&mut tx)
+        .await?;
+    if !pending {
+        return Ok(false);
+    }
+    insert_verification_token(&mut tx, mail, token_hash).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Earlier tokens of the mail stop working, only the latest link verifies it.
+async fn insert_verification_token(
+    conn: &mut PgConnection,
+    mail: &str,
+    token_hash: &str
+) -> Result<(), Error> {
+    sqlx::query!("DELETE FROM mail_verification_token WHERE mail = $1", mail)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query!(
+        "INSERT INTO mail_verification_token (token_hash, mail, expires)
+         VALUES ($1, $2, now() + make_interval(hours => $3))",
+        token_hash,
+        mail,
+        VERIFICATION_TTL_HOURS
+    )
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Consumes the token, it cannot be used a second time even if it had expired.
+pub async fn verify_mail(
+    token_hash: &str,
+    data: &web::Data<AppState>
+) -> Result<Verification, Error> {
+    let mut tx = data.db.begin().await?;
+    let token = sqlx::query!(
+        r#"DELETE FROM mail_verification_token WHERE token_hash = $1
+         RETURNING mail, expires > now() as "valid!""#,
+        token_hash
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let verification = match token {
+        None => Verification::Invalid,
+        Some(token) if !token.valid => Verification::Expired,
+        Some(token) => {
+            sqlx::query!(
+                "UPDATE user_account_mails SET is_verified = true WHERE mail = $1",
+                token.mail
+            )
+                .execute(&mut tx)
+                .await?;
+            Verification::Verified(token.mail)
+        }
+    };
+    tx.commit().await?;
+    Ok(verification)
+}
+
+pub async fn delete_mail(
+    user_id: Uuid,
+    mail: &str,
+    data: &web::Data<AppState>
+) -> Result<MailUpdate, Error> {
+    let mut tx = data.db.begin().await?;
+    let is_primary = sqlx::query_scalar!(
+        "SELECT is_primary FROM user_account_mails WHERE mail = $1 AND user_account_pk = $2 FOR UPDATE",
+        mail,
+        user_id
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let update = match is_primary {
+        None => MailUpdate::NotFound,
+        Some(true) => MailUpdate::Primary,
+        Some(false) => {
+            sqlx::query!("DELETE FROM user_account_mails WHERE mail = $1", mail)
+                .execute(&mut tx)
+                .await?;
+            MailUpdate::Updated
+        }
+    };
+    tx.commit().await?;
+    Ok(update)
+}
+
+pub async fn set_primary_mail(
+    user_id: Uuid,
+    mail: &str,
+    data: &web::Data<AppState>
+) -> Result<MailUpdate, Error> {
+    let mut tx = data.db.begin().await?;
+    let is_verified = sqlx::query_scalar!(
+        "SELECT is_verified FROM user_account_mails WHERE mail = $1 AND user_account_pk = $2 FOR UPDATE",
+        mail,
+        user_id
+    )
+        .fetch_optional(&mut tx)
+        .await?;
+    let update = match is_verified {
+        None => MailUpdate::NotFound,
+        Some(false) => MailUpdate::Unverified,
+        Some(true) => {
+            sqlx::query!(
+                "UPDATE user_account_mails SET is_primary = false WHERE user_account_pk = $1 AND is_primary",
+                user_id
+            )
+                .execute(&mut tx)
+                .await?;
+            sqlx::query!(
+                "UPDATE user_account_mails SET is_primary = true WHERE mail = $1",
+                mail
+            )
+                .execute(&mut tx)
+                .await?;
+            MailUpdate::Updated
+        }
+    };
+    tx.commit().await?;
+    Ok(update)
+}
+
+pub struct ProfileStats {
+    pub prints_made: i64,
+    pub prints_of_files: i64,
+    pub average_rating: Option<f64>,
+}
+
+pub struct Avatar {
+    pub id: Uuid,
+    pub content_type: String,
+}
+
+pub async fn select_profile(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<UserProfileModel>, Error> {
+    sqlx::query_as!(
+        UserProfileModel,
+        r#"SELECT id, user_name, display_name, bio, location, website, avatar_id IS NOT NULL as "has_avatar!"
+        FROM user_account WHERE id = $1"#,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Only supplied fields change, empty strings clear them.
+pub async fn update_profile(
+    user_id: Uuid,
+    profile: &UpdateProfile,
+    data: &web::Data<AppState>
+) -> Result<bool, Error> {
+    let rows_affected = sqlx::query!(
+        "UPDATE user_account SET
+            display_name = CASE WHEN $1::varchar IS NULL THEN display_name ELSE NULLIF($1, '') END,
+            bio = CASE WHEN $2::text IS NULL THEN bio ELSE NULLIF($2, '') END,
+            location = CASE WHEN $3::varchar IS NULL THEN location ELSE NULLIF($3, '') END,
+            website = CASE WHEN $4::varchar IS NULL THEN website ELSE NULLIF($4, '') END
+         WHERE id = $5",
+        profile.display_name.as_deref().map(str::trim),
+        profile.bio.as_deref().map(str::trim),
+        profile.location.as_deref().map(str::trim),
+        profile.website.as_deref().map(str::trim),
+        user_id
+    )
+        .execute(&data.db)
+        .await?
+        .rows_affected();
+    Ok(rows_affected > 0)
+}
+
+pub async fn select_profile_stats(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<ProfileStats, Error> {
+    sqlx::query_as!(
+        ProfileStats,
+        r#"WITH public_files AS (
+            SELECT f.* FROM file f
+                JOIN files_per_user fpu ON fpu.files_pk = f.id
+            WHERE fpu.user_account_pk = $1 AND fpu.roles_pk = 'owner' AND f.is_public AND NOT f.is_hidden
+        )
+        SELECT
+            (SELECT count(*) FROM print WHERE user_account_fk = $1) as "prints_made!",
+            (SELECT count(*) FROM print pr
+                JOIN gcode g ON g.id = pr.gcode_fk
+                WHERE g.file_pk IN (SELECT id FROM public_files)) as "prints_of_files!",
+            (SELECT avg(average_rating)::float8 FROM public_files WHERE average_rating > 0) as average_rating"#,
+        user_id
+    )
+        .fetch_one(&data.db)
+        .await
+}
+
+pub async fn select_public_files_of_user(
+    user_id: Uuid,
+    limit: usize,
+    offset: usize,
+    data: &web::Data<AppState>
+) -> Result<Vec<FilePublicResponseModel>, Error> {
+    sqlx::query_as!(
+        FilePublicResponseModel,
+        r#"SELECT f.id, f.fullname, f.created, f.sizebytes, f.downloads, f.average_rating,
+            f.is_downloadable, ua.user_name as "owner?", f.version
+        FROM file f
+            JOIN files_per_user fpu ON fpu.files_pk = f.id
+            JOIN user_account ua ON ua.id = fpu.user_account_pk
+        WHERE fpu.user_account_pk = $1 AND fpu.roles_pk = 'owner' AND f.is_public AND NOT f.is_hidden
+        ORDER BY f.created DESC LIMIT $2 OFFSET $3"#,
+        user_id,
+        limit as i32,
+        offset as i32
+    )
+        .fetch_all(&data.db)
+        .await
+}
+
+pub async fn select_avatar(
+    user_id: Uuid,
+    data: &web::Data<AppState>
+) -> Result<Option<Avatar>, Error> {
+    sqlx::query_as!(
+        Avatar,
+        r#"SELECT avatar_id as "id!", avatar_content_type as "content_type!" FROM user_account
+        WHERE id = $1 AND avatar_id IS NOT NULL AND avatar_content_type IS NOT NULL"#,
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+}
+
+/// Points the account at a new avatar blob and returns the previous one, if any.
+pub async fn replace_avatar(
+    user_id: Uuid,
+    avatar: Option<&Avatar>,
+    data: &web::Data<AppState>
+) -> Result<Option<Uuid>, Error> {
+    sqlx::query_scalar!(
+        "UPDATE user_account ua SET avatar_id = $1, avatar_content_type = $2
+         FROM user_account previous
+         WHERE ua.id = $3 AND previous.id = ua.id
+         RETURNING previous.avatar_id",
+        avatar.map(|avatar| avatar.id),
+        avatar.map(|avatar| avatar.content_type.as_str()),
+        user_id
+    )
+        .fetch_optional(&data.db)
+        .await
+        .map(Option::flatten)
+}
@@ -0,0 +1,404 @@
+use crate::{
+    auth::AuthUser,
+    connector::{job_filename, Connector},
+    model::JobStatus,
+    schema::{ConnectorSettings, CreatePrintJob, CreateUserPrinter, ReorderPrintJobs, UpdatePrintJobStatus},
+    AppState,
+};
+
+use actix_web::{delete, get, patch, post, put, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::gcode_queries::select_downloadable_gcode_file;
+use crate::query_service::queue_queries::*;
+
+fn queue_error(e: sqlx::Error) -> HttpResponse {
+    let message = e.to_string();
+    if message.contains("duplicate key value violates unique constraint") {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Printer with that name already exists"}));
+    }
+    if message.contains("violates foreign key constraint") {
+        return HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Referenced printer, gcode or material does not exist"}),
+        );
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn printer_not_found(id: Uuid) -> HttpResponse {
+    let message = format!("Printer with ID: {} not found", id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+fn job_update_response(job_id: Uuid, result: Result<JobUpdate, sqlx::Error>) -> HttpResponse {
+    match result {
+        Ok(JobUpdate::Updated(job)) => HttpResponse::Ok().json(job),
+        Ok(JobUpdate::NotFound) => {
+            let message = format!("Job with ID: {} not found", job_id);
+            HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+        }
+        Ok(JobUpdate::InvalidTransition(current)) => {
+            let message = format!("Job is {:?} and cannot change to the requested status", current);
+            HttpResponse::Conflict().json(json!({"status": "fail","message": message}))
+        }
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = UserPrinterModel),
+(status = 400, description = "Empty or duplicate name, or unknown printer model", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreateUserPrinter, description="all parameters are required"),
+)]
+#[post("/user-printers")]
+pub async fn create_user_printer(
+    user: AuthUser,
+    body: web::Json<CreateUserPrinter>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if body.name.trim().is_empty() {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "name must not be empty"}));
+    }
+    let query_result = match insert_user_printer(&body, user.id, &data).await {
+        Ok(id) => select_user_printer(id, user.id, &data).await,
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(printer)) => HttpResponse::Created().json(printer),
+        Ok(None) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": "Created printer could not be read"})),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<UserPrinterModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/user-printers")]
+pub async fn get_user_printers(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_user_printers(user.id, &data).await {
+        Ok(printers) => HttpResponse::Ok().json(printers),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Deleted together with its jobs"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[delete("/user-printers/{id}")]
+pub async fn remove_user_printer(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    match delete_user_printer(printer_id, user.id, &data).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => printer_not_found(printer_id),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, running and queued jobs first in print order", body = Vec<PrintJobModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/user-printers/{id}/jobs")]
+pub async fn get_print_jobs(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    match select_user_printer(printer_id, user.id, &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return printer_not_found(printer_id),
+        Err(e) => return queue_error(e),
+    }
+    match select_jobs(printer_id, &data).await {
+        Ok(jobs) => HttpResponse::Ok().json(jobs),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created", body = PrintJobModel),
+(status = 400, description = "Unknown material", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found, or gcode not found or not downloadable by the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = CreatePrintJob, description="gcodeId is required"),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/user-printers/{id}/jobs")]
+pub async fn enqueue_print_job(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<CreatePrintJob>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    match select_user_printer(printer_id, user.id, &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return printer_not_found(printer_id),
+        Err(e) => return queue_error(e),
+    }
+    // the connector uploads the G-code to the printer, which takes the right to download it
+    match select_downloadable_gcode_file(body.gcode_id, Some(user.id), &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            let message = format!("Gcode with ID: {} not found", body.gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => return queue_error(e),
+    }
+    match insert_job(printer_id, &body, &data).await {
+        Ok(job) => HttpResponse::Created().json(job),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the reordered queue", body = Vec<PrintJobModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = ReorderPrintJobs),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/user-printers/{id}/jobs/order")]
+pub async fn reorder_print_jobs(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<ReorderPrintJobs>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    match select_user_printer(printer_id, user.id, &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return printer_not_found(printer_id),
+        Err(e) => return queue_error(e),
+    }
+    let query_result = match reorder_jobs(printer_id, &body.job_ids, &data).await {
+        Ok(()) => select_jobs(printer_id, &data).await,
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(jobs) => HttpResponse::Ok().json(jobs),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintJobModel),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Job not found", body = String),
+(status = 409, description = "Job already finished", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Job Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/jobs/{id}/cancel")]
+pub async fn cancel_print_job(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+    let update = UpdatePrintJobStatus {
+        status: JobStatus::Cancelled,
+        nozzle_size_mm: None,
+        bed_temp_celsius: None,
+        extruder_temp: None,
+        failure_category: None,
+        notes: None,
+    };
+    job_update_response(job_id, update_job_status(job_id, Some(user.id), &update, &data).await)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, finished jobs link the created print", body = PrintJobModel),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Job not found", body = String),
+(status = 409, description = "Status change not allowed", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdatePrintJobStatus, description="print settings are only used when the job finishes"),
+params(
+("id" = String, Path, description = "Job Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[patch("/jobs/{id}/status")]
+pub async fn edit_print_job_status(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdatePrintJobStatus>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let job_id = path.into_inner();
+    job_update_response(job_id, update_job_status(job_id, Some(user.id), &body, &data).await)
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = UserPrinterModel),
+(status = 400, description = "Connector kind without URL", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = ConnectorSettings, description="kind null removes the connector"),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[put("/user-printers/{id}/connector")]
+pub async fn configure_printer_connector(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<ConnectorSettings>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    let mut settings = body.into_inner();
+    match (&settings.kind, &settings.url) {
+        (None, _) => {
+            settings.url = None;
+            settings.api_key = None;
+        }
+        (Some(_), Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {}
+        (Some(_), _) => {
+            return HttpResponse::BadRequest()
+                .json(json!({"status": "fail","message": "url must be an http(s) URL"}));
+        }
+    }
+    let query_result = match update_connector(printer_id, user.id, &settings, &data).await {
+        Ok(true) => select_user_printer(printer_id, user.id, &data).await,
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(printer)) => HttpResponse::Ok().json(printer),
+        Ok(None) => printer_not_found(printer_id),
+        Err(e) => queue_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the job sent to the printer", body = PrintJobModel),
+(status = 400, description = "No connector configured", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Printer not found or nothing queued", body = String),
+(status = 409, description = "Printer is already printing", body = String),
+(status = 502, description = "Printer host rejected the job", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[post("/user-printers/{id}/start")]
+pub async fn start_next_print_job(
+    user: AuthUser,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let printer_id = path.into_inner();
+    match select_user_printer(printer_id, user.id, &data).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return printer_not_found(printer_id),
+        Err(e) => return queue_error(e),
+    }
+    let config = match select_connector(printer_id, &data).await {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            return HttpResponse::BadRequest()
+                .json(json!({"status": "fail","message": "Printer has no connector configured"}));
+        }
+        Err(e) => return queue_error(e),
+    };
+    let job = match select_next_job(printer_id, &data).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .json(json!({"status": "fail","message": "No queued job for this printer"}));
+        }
+        Err(e) => return queue_error(e),
+    };
+    let file = match select_downloadable_gcode_file(job.gcode_id, Some(user.id), &data).await {
+        Ok(Some(file)) => file,
+        Ok(None) => {
+            let message = format!("Gcode with ID: {} not found", job.gcode_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
+        }
+        Err(e) => return queue_error(e),
+    };
+    let content = match data.storage.read(file.id).await {
+        Ok(content) => content,
+        Err(e) => {
+            return HttpResponse::InternalServerError()
+                .json(json!({"status": "error","message": format!("{:?}", e)}));
+        }
+    };
+    // the job is marked printing before the upload, so a second start sees the printer busy
+    let job = match start_job(printer_id, job.id, &data).await {
+        Ok(Some(job)) => job,
+        Ok(None) => {
+            return HttpResponse::Conflict()
+                .json(json!({"status": "fail","message": "Printer is already printing"}));
+        }
+        Err(e) => return queue_error(e),
+    };
+    let filename = job_filename(job.id, &file.fullname);
+    if let Err(e) = Connector::new(config).start_print(&filename, content).await {
+        if let Err(e) = requeue_job(job.id, &data).await {
+            return queue_error(e);
+        }
+        return HttpResponse::BadGateway()
+            .json(json!({"status": "fail","message": e.to_string()}));
+    }
+    HttpResponse::Ok().json(job)
+}
@@ -0,0 +1,149 @@
+use crate::model::{PrintSettingsRow, RecommendedSettings, SettingRange};
+use uuid::Uuid;
+
+/// Extra weight of a print made on the requested printer or with the requested material.
+const MATCH_WEIGHT: f64 = 2.0;
+
+/// Derives settings from successful prints. Every print counts once, prints on the target
+/// printer or with the target material count more, so the median leans towards them while
+/// the range still covers everything that worked.
+pub fn recommend(
+    rows: &[PrintSettingsRow],
+    printer_id: Option<Uuid>,
+    material_id: Option<Uuid>,
+) -> RecommendedSettings {
+    let same_printer = |row: &PrintSettingsRow| printer_id.is_some() && row.printer_id == printer_id;
+    let same_material = |row: &PrintSettingsRow| material_id.is_some() && row.material_id == material_id;
+    let weights: Vec<f64> = rows
+        .iter()
+        .map(|row| {
+            let mut weight = 1.0;
+            if same_printer(row) {
+                weight += MATCH_WEIGHT;
+            }
+            if same_material(row) {
+                weight += MATCH_WEIGHT;
+            }
+            weight
+        })
+        .collect();
+
+    let setting = |value: fn(&PrintSettingsRow) -> Option<f64>| {
+        let samples = rows
+            .iter()
+            .zip(&weights)
+            .filter_map(|(row, weight)| value(row).map(|value| (value, *weight)))
+            .collect();
+        weighted_range(samples)
+    };
+
+    RecommendedSettings {
+        samples: rows.len(),
+        matching_printer: rows.iter().filter(|row| same_printer(row)).count(),
+        matching_material: rows.iter().filter(|row| same_material(row)).count(),
+        nozzle_size_mm: setting(|row| row.nozzle_size_mm),
+        bed_temp_celsius: setting(|row| row.bed_temp_celsius.map(f64::from)),
+        extruder_temp: setting(|row| row.extruder_temp.map(f64::from)),
+    }
+}
+
+fn weighted_range(mut samples: Vec<(f64, f64)>) -> Option<SettingRange> {
+    samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let min = samples.first()?.0;
+    let max = samples.last()?.0;
+    let half = samples.iter().map(|(_, weight)| weight).sum::<f64>() / 2.0;
+
+    let mut cumulative = 0.0;
+    let mut median = max;
+    for (value, weight) in &samples {
+        cumulative += weight;
+        if cumulative >= half {
+            median = *value;
+            break;
+        }
+    }
+
+    Some(SettingRange { median, min, max })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(printer_id: Option<Uuid>, material_id: Option<Uuid>, extruder_temp: i32) -> PrintSettingsRow {
+        PrintSettingsRow {
+            printer_id,
+            material_id,
+            nozzle_size_mm: Some(0.4),
+            bed_temp_celsius: None,
+            extruder_temp: Some(extruder_temp),
+        }
+    }
+
+    fn unweighted(values: &[f64]) -> Vec<(f64, f64)> {
+        values.iter().map(|value| (*value, 1.0)).collect()
+    }
+
+    #[test]
+    fn empty_input_has_no_range() {
+        assert!(weighted_range(Vec::new()).is_none());
+
+        let settings = recommend(&[], Some(Uuid::new_v4()), None);
+        assert_eq!(settings.samples, 0);
+        assert_eq!(settings.matching_printer, 0);
+        assert!(settings.nozzle_size_mm.is_none());
+        assert!(settings.extruder_temp.is_none());
+    }
+
+    #[test]
+    fn odd_sample_count_takes_the_middle_value() {
+        let range = weighted_range(unweighted(&[215.0, 200.0, 210.0])).unwrap();
+        assert_eq!(range.median, 210.0);
+        assert_eq!(range.min, 200.0);
+        assert_eq!(range.max, 215.0);
+    }
+
+    #[test]
+    fn even_sample_count_takes_the_lower_middle_value() {
+        let range = weighted_range(unweighted(&[220.0, 200.0, 210.0, 215.0])).unwrap();
+        assert_eq!(range.median, 210.0);
+        assert_eq!(range.min, 200.0);
+        assert_eq!(range.max, 220.0);
+    }
+
+    #[test]
+    fn matching_printer_pulls_the_median_towards_it() {
+        let printer = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let rows = [
+            row(Some(other), None, 200),
+            row(Some(other), None, 205),
+            row(Some(printer), None, 230),
+        ];
+
+        let plain = recommend(&rows, None, None).extruder_temp.unwrap();
+        assert_eq!(plain.median, 205.0);
+
+        let settings = recommend(&rows, Some(printer), None);
+        assert_eq!(settings.matching_printer, 1);
+        let weighted = settings.extruder_temp.unwrap();
+        assert_eq!(weighted.median, 230.0);
+        assert_eq!(weighted.min, 200.0);
+        assert_eq!(weighted.max, 230.0);
+    }
+
+    #[test]
+    fn matching_material_pulls_the_median_towards_it() {
+        let material = Uuid::new_v4();
+        let rows = [
+            row(None, Some(material), 190),
+            row(None, None, 210),
+            row(None, None, 215),
+        ];
+
+        let settings = recommend(&rows, None, Some(material));
+        assert_eq!(settings.matching_material, 1);
+        assert_eq!(settings.extruder_temp.unwrap().median, 190.0);
+        assert!(settings.bed_temp_celsius.is_none());
+    }
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
+use crate::model::{ApiKeyScope, ConnectorKind, FailureCategory, JobStatus, MaterialType, OrganizationRole, UserRole};
 
 #[derive(Deserialize, Debug)]
 pub struct FilterOptions {
@@ -13,19 +14,24 @@ pub struct ParamOptions {
     pub id: String,
 }
 
+/// `downloads` and `averageRating` are maintained by the server, so unknown fields are rejected
+/// instead of being silently ignored.
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct UpdateFile {
     pub fullname: Option<String>,
-    pub downloads: Option<i32>,
-    pub average_rating: Option<f32>,
+    #[serde(rename = "isPublic")]
+    pub is_public: Option<bool>,
+    #[serde(rename = "isDownloadable")]
+    pub is_downloadable: Option<bool>,
+    pub description: Option<String>,
+    pub license: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, ToSchema)]
 pub struct CreateFile {
     pub fullname: String,
     pub sizebytes: i64,
-    #[serde(rename = "ownerUserId")]
-    pub owner_user_id: String,
     #[serde(rename = "isDownloadable")]
     pub is_downloadable: bool,
     #[serde(rename = "isPublic")]
@@ -55,3 +61,356 @@ pub struct CreateFilePermissionSchema {
     pub roles_pk: String,
     pub files_pk: Uuid,
 }
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkUpdateFiles {
+    pub ids: Vec<Uuid>,
+    #[serde(rename = "isPublic")]
+    pub is_public: Option<bool>,
+    #[serde(rename = "isDownloadable")]
+    pub is_downloadable: Option<bool>,
+    #[serde(rename = "addTags")]
+    pub add_tags: Option<Vec<String>>,
+    #[serde(rename = "removeTags")]
+    pub remove_tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct BulkFileIds {
+    pub ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateCollection {
+    pub name: String,
+    #[serde(rename = "fileIds")]
+    pub file_ids: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ImportOptions {
+    #[serde(rename = "isPublic")]
+    pub is_public: Option<bool>,
+    #[serde(rename = "isDownloadable")]
+    pub is_downloadable: Option<bool>,
+    /// Comma separated list of tags applied to every imported file
+    pub tags: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreatePrint {
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Uuid,
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    #[serde(rename = "printerId")]
+    pub printer_id: Option<Uuid>,
+    #[serde(rename = "nozzleSizeMm")]
+    pub nozzle_size_mm: Option<f64>,
+    #[serde(rename = "bedTempCelsius")]
+    pub bed_temp_celsius: Option<i32>,
+    #[serde(rename = "extruderTemp")]
+    pub extruder_temp: Option<i32>,
+    pub successful: bool,
+    /// Only allowed for unsuccessful prints
+    #[serde(rename = "failureCategory")]
+    pub failure_category: Option<FailureCategory>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdatePrint {
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    #[serde(rename = "printerId")]
+    pub printer_id: Option<Uuid>,
+    #[serde(rename = "nozzleSizeMm")]
+    pub nozzle_size_mm: Option<f64>,
+    #[serde(rename = "bedTempCelsius")]
+    pub bed_temp_celsius: Option<i32>,
+    #[serde(rename = "extruderTemp")]
+    pub extruder_temp: Option<i32>,
+    pub successful: Option<bool>,
+    #[serde(rename = "failureCategory")]
+    pub failure_category: Option<FailureCategory>,
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CatalogSearchOptions {
+    pub q: Option<String>,
+    #[serde(rename = "brandId")]
+    pub brand_id: Option<Uuid>,
+    #[serde(rename = "matType")]
+    pub mat_type: Option<MaterialType>,
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateBrand {
+    #[serde(rename = "fullName")]
+    pub full_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreatePrinter {
+    pub model: String,
+    #[serde(rename = "brandId")]
+    pub brand_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdatePrinter {
+    pub model: Option<String>,
+    #[serde(rename = "brandId")]
+    pub brand_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateMaterial {
+    pub description: String,
+    #[serde(rename = "matType")]
+    pub mat_type: MaterialType,
+    #[serde(rename = "brandId")]
+    pub brand_id: Uuid,
+    #[serde(rename = "densityGCm3")]
+    pub density_g_cm3: Option<f64>,
+    /// Defaults to 1.75
+    #[serde(rename = "diameterMm")]
+    pub diameter_mm: Option<f64>,
+    #[serde(rename = "pricePerKg")]
+    pub price_per_kg: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateMaterial {
+    pub description: Option<String>,
+    #[serde(rename = "matType")]
+    pub mat_type: Option<MaterialType>,
+    #[serde(rename = "brandId")]
+    pub brand_id: Option<Uuid>,
+    #[serde(rename = "densityGCm3")]
+    pub density_g_cm3: Option<f64>,
+    #[serde(rename = "diameterMm")]
+    pub diameter_mm: Option<f64>,
+    #[serde(rename = "pricePerKg")]
+    pub price_per_kg: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecommendationOptions {
+    #[serde(rename = "printerId")]
+    pub printer_id: Option<Uuid>,
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateUserPrinter {
+    pub name: String,
+    #[serde(rename = "printerId")]
+    pub printer_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreatePrintJob {
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Uuid,
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    /// Higher priorities are printed first, defaults to 0
+    pub priority: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ReorderPrintJobs {
+    /// Queued jobs in the desired order, jobs not listed keep their position
+    #[serde(rename = "jobIds")]
+    pub job_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdatePrintJobStatus {
+    pub status: JobStatus,
+    #[serde(rename = "nozzleSizeMm")]
+    pub nozzle_size_mm: Option<f64>,
+    #[serde(rename = "bedTempCelsius")]
+    pub bed_temp_celsius: Option<i32>,
+    #[serde(rename = "extruderTemp")]
+    pub extruder_temp: Option<i32>,
+    #[serde(rename = "failureCategory")]
+    pub failure_category: Option<FailureCategory>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ConnectorSettings {
+    /// `null` removes the connector
+    pub kind: Option<ConnectorKind>,
+    /// Base URL of the OctoPrint or Moonraker host, e.g. http://octopi.local
+    pub url: Option<String>,
+    #[serde(rename = "apiKey")]
+    pub api_key: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LayerRangeOptions {
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FilamentUsageOptions {
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FilamentReportOptions {
+    /// Number of months back from the current one, defaults to 12
+    pub months: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct LinkGcodeVariant {
+    #[serde(rename = "gcodeId")]
+    pub gcode_id: Uuid,
+    /// Printer the G-code was sliced for
+    #[serde(rename = "printerId")]
+    pub printer_id: Option<Uuid>,
+    /// Material the G-code was sliced for
+    #[serde(rename = "materialId")]
+    pub material_id: Option<Uuid>,
+    /// Short description of the slicer settings, e.g. "0.2mm, 20% infill"
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct AddMail {
+    pub mail: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct VerifyMailOptions {
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct Login {
+    pub mail: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ChangePassword {
+    #[serde(rename = "currentPassword")]
+    pub current_password: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct PasswordResetRequest {
+    pub mail: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct PasswordResetConfirm {
+    pub token: String,
+    #[serde(rename = "newPassword")]
+    pub new_password: String,
+}
+
+/// An empty string clears a field.
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct UpdateProfile {
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub location: Option<String>,
+    pub website: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UserSearchOptions {
+    pub q: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct DeleteAccount {
+    /// Required if the account has a password, without one the session must be at most 5 minutes old
+    pub password: Option<String>,
+    /// Files only this account owns go to this user; without it they are deleted
+    #[serde(rename = "transferFilesTo")]
+    pub transfer_files_to: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Never expires if left out
+    pub expires: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateUserRole {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SuspendUser {
+    /// Shown to admins in the user list
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateAbuseReport {
+    pub reason: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReportFilterOptions {
+    /// Defaults to open reports only
+    pub resolved: Option<bool>,
+    pub page: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateOrganization {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct UpdateOrganizationMember {
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct AssignFileOrganization {
+    #[serde(rename = "organizationId")]
+    pub organization_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CreateGroup {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct GrantFileRole {
+    /// 'owner', 'download' or 'read'
+    pub role: String,
+}
+
+/// Query of the redirect back from the OpenID Connect provider.
+#[derive(Deserialize, Debug)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
@@ -0,0 +1,167 @@
+use crate::{
+    auth::AuthUser,
+    prints_controller::require_file_visible,
+    recommendation::recommend,
+    schema::{FilamentReportOptions, FilterOptions, RecommendationOptions},
+    AppState,
+};
+
+use actix_web::{get, web, HttpResponse, Responder};
+use serde_json::json;
+use uuid::Uuid;
+use crate::query_service::stats_queries::*;
+
+async fn stats_response(
+    file_id: Option<Uuid>,
+    printer_id: Option<Uuid>,
+    material_id: Option<Uuid>,
+    user: Option<AuthUser>,
+    data: web::Data<AppState>,
+) -> HttpResponse {
+    let user_id = user.map(|user| user.id);
+    match select_print_stats(file_id, printer_id, material_id, user_id, &data).await {
+        Ok(stats) => HttpResponse::Ok().json(stats),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintStatsModel),
+(status = 404, description = "File not found or not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/files/{id}/print-stats")]
+pub async fn get_file_print_stats(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_visible(file_id, user, &data).await {
+        return response;
+    }
+    stats_response(Some(file_id), None, None, user, data).await
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintStatsModel),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Printer Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/printers/{id}/print-stats")]
+pub async fn get_printer_print_stats(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    stats_response(None, Some(path.into_inner()), None, user, data).await
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = PrintStatsModel),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "Material Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/materials/{id}/print-stats")]
+pub async fn get_material_print_stats(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    stats_response(None, None, Some(path.into_inner()), user, data).await
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = Vec<FilePrintCountModel>),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/stats/most-printed")]
+pub async fn get_most_printed_files(
+    opts: web::Query<FilterOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let limit = opts.limit.unwrap_or(10);
+    let offset = (opts.page.unwrap_or(1).max(1) - 1) * limit;
+
+    match select_most_printed(limit, offset, &data).await {
+        Ok(files) => HttpResponse::Ok().json(files),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = RecommendedSettings),
+(status = 404, description = "File not found or not visible to the caller", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "File Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)"),
+("printerId" = Option<String>, Query, description = "Prefer prints made on this printer"),
+("materialId" = Option<String>, Query, description = "Prefer prints made with this material")
+))]
+#[get("/files/{id}/recommended-settings")]
+pub async fn get_recommended_settings(
+    user: Option<AuthUser>,
+    path: web::Path<Uuid>,
+    opts: web::Query<RecommendationOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let file_id = path.into_inner();
+    if let Err(response) = require_file_visible(file_id, user, &data).await {
+        return response;
+    }
+
+    match select_successful_settings(file_id, &data).await {
+        Ok(rows) => HttpResponse::Ok().json(recommend(&rows, opts.printer_id, opts.material_id)),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, filament grams and cost of the caller's prints per month", body = Vec<MonthlyFilamentUsage>),
+(status = 400, description = "Invalid number of months", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("months" = Option<i32>, Query, description = "Months to report including the current one, 1 to 120, defaults to 12")
+))]
+#[get("/stats/filament-usage")]
+pub async fn get_filament_report(
+    user: AuthUser,
+    opts: web::Query<FilamentReportOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let months = opts.months.unwrap_or(12);
+    if !(1..=120).contains(&months) {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "months must be between 1 and 120"}));
+    }
+    match select_monthly_filament_usage(user.id, months, &data).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
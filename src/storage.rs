@@ -0,0 +1,49 @@
+use std::io;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Image formats accepted for print photos and avatars.
+pub const IMAGE_CONTENT_TYPES: [&str; 3] = ["image/jpeg", "image/png", "image/webp"];
+pub const MAX_IMAGE_BYTES: usize = 10 * 1024 * 1024;
+
+/// File extension for one of the `IMAGE_CONTENT_TYPES`.
+pub fn image_extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/webp" => "webp",
+        _ => "jpg",
+    }
+}
+
+/// Keeps file contents on the local disk, one blob per id below `STORAGE_DIR`.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    root: PathBuf,
+}
+
+impl Storage {
+    pub fn from_env() -> Self {
+        let root = std::env::var("STORAGE_DIR").unwrap_or_else(|_| "./storage".to_string());
+        Storage { root: PathBuf::from(root) }
+    }
+
+    pub fn path(&self, id: Uuid) -> PathBuf {
+        self.root.join(id.to_string())
+    }
+
+    pub async fn write(&self, id: Uuid, bytes: &[u8]) -> io::Result<()> {
+        tokio::fs::create_dir_all(&self.root).await?;
+        tokio::fs::write(self.path(id), bytes).await
+    }
+
+    pub async fn read(&self, id: Uuid) -> io::Result<Vec<u8>> {
+        tokio::fs::read(self.path(id)).await
+    }
+
+    pub async fn delete(&self, id: Uuid) -> io::Result<()> {
+        match tokio::fs::remove_file(self.path(id)).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
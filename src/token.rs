@@ -0,0 +1,11 @@
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// A random secret handed out once, e.g. in a verification link. Only its [`hash`] is stored.
+pub fn generate() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub fn hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -1,76 +1,113 @@
-use crate::model::UserModel;
-use crate::{schema::FilterOptions, AppState, GetIdSchema};
-use actix_web::{get, post, web, HttpResponse, Responder};
+use crate::model::{PublicProfileModel, UserRole};
+use crate::storage::{IMAGE_CONTENT_TYPES, MAX_IMAGE_BYTES};
+use crate::{
+    auth::{AdminUser, AuthUser},
+    auth_controller::session_required,
+    mailer::Mail,
+    schema::FilterOptions,
+    token, AppState, GetIdSchema,
+};
+use actix_web::{delete, get, http::header, patch, post, put, web, HttpRequest, HttpResponse, Responder};
 use serde_json::json;
-use crate::schema::CreateUser;
+use uuid::Uuid;
+use crate::schema::{AddMail, CreateUser, UpdateProfile, UserSearchOptions, VerifyMailOptions};
+use crate::query_service::user_queries::*;
 
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, all accounts", body = Vec<UserModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Admin role required", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
 #[get("/users")]
 pub async fn user_list_handler(
+    _admin: AdminUser,
     opts: web::Query<FilterOptions>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let limit = opts.limit.unwrap_or(10);
     let offset = (opts.page.unwrap_or(1) - 1) * limit;
-    let query_result = sqlx::query_as!(
-        UserModel,
-        "SELECT * FROM user_account LIMIT $1 OFFSET $2",
-        limit as i32,
-        offset as i32
-    )
-    .fetch_all(&data.db)
-    .await;
+    let query_result = select_users(limit, offset, &data).await;
 
     if query_result.is_err() {
-        let message = "Something bad happened while fetching all note items";
+        let message = "Something bad happened while fetching all users";
         return HttpResponse::InternalServerError()
             .json(json!({"status": "error","message": message}));
     }
 
-    let notes = query_result.unwrap();
+    let users = query_result.unwrap();
 
     let json_response = json!({
         "status": "success",
-        "results": notes.len(),
-        "users": notes
+        "results": users.len(),
+        "users": users
     });
     HttpResponse::Ok().json(json_response)
 }
 
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, at most 20 matches by display or user name", body = Vec<UserSearchResult>),
+(status = 400, description = "Query too short", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(("q" = String, Query, description = "Part of the display or user name, at least 2 characters")))]
+#[get("/users/search")]
+pub async fn search_user_handler(
+    _user: AuthUser,
+    opts: web::Query<UserSearchOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let query = opts.q.trim();
+    if query.chars().count() < MIN_SEARCH_LENGTH {
+        let message = format!("q must have at least {} characters", MIN_SEARCH_LENGTH);
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    match search_users(query, &data).await {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
+    }
+}
+
 #[utoipa::path(responses(
 (status = 200, description = "OK, User Uuid", body = IdSchema),
-(status = 404, description = "User not found", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "Not one of the caller's mails, or for admins no user with that verified mail", body = String),
 (status = 500, description = "Internal server error", body = String)),
 params(("mail" = String, Path, description = "User Mail")))]
 #[get("/users/{mail}")]
 pub async fn get_user_id_by_mail(
+    user: AuthUser,
     path: web::Path<String>,
     data: web::Data<AppState>,
 ) -> impl Responder {
     let mail = path.into_inner();
-    let query_result = sqlx::query_as!(
-        GetIdSchema,
-        "
-        SELECT id FROM user_account ua
-            LEFT JOIN user_account_mails um ON ua.id = um.user_account_pk
-            WHERE um.mail = $1",
-        mail
-    )
-    .fetch_one(&data.db)
-    .await;
+    let requester_is_admin = user.role == UserRole::Admin;
+    let query_result = match normalize_mail(&mail) {
+        Some(normalized) => select_user_id_by_mail(&normalized, user.id, requester_is_admin, &data).await,
+        None => Ok(None),
+    };
 
     match query_result {
-        Ok(note) => HttpResponse::Ok().json(note),
-        Err(_) => {
+        Ok(Some(id)) => HttpResponse::Ok().json(GetIdSchema { id }),
+        Ok(None) => {
             let message = format!("User with mail: {} not found", mail);
             HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
         }
+        Err(e) => HttpResponse::InternalServerError()
+            .json(json!({"status": "error","message": format!("{:?}", e)})),
     }
 }
 
 
 #[utoipa::path(responses(
-(status = 200, description = "OK", body = IdSchema),
-(status = 404, description = "Files not found", body = String),
+(status = 200, description = "OK, a verification link is sent to the mail", body = IdSchema),
+(status = 400, description = "Invalid mail or mail already in use", body = String),
 (status = 500, description = "Internal server error", body = String)
 ),
 request_body(content = CreateUser),
@@ -80,35 +117,468 @@ pub(crate) async fn create_user(
     body: web::Json<CreateUser>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    println!("username: {}, mail: {}", body.user_name, body.mail);
-    let query_result = sqlx::query_as!(
-        GetIdSchema,
-        "WITH inserted_user AS (
-            INSERT INTO user_account (user_name) VALUES ($1)
-            RETURNING id
-            )
-        INSERT INTO user_account_mails (mail, user_account_pk)
-            VALUES ($2, (SELECT id FROM inserted_user))
-        RETURNING user_account_mails.user_account_pk as id",
-        body.user_name,
-        body.mail
-    )
-        .fetch_one(&data.db)
-        .await;
-
-    return match query_result {
-        Ok(user) => HttpResponse::Ok().json(user),
+    let mail = match normalize_mail(&body.mail) {
+        Some(mail) => mail,
+        None => return invalid_mail(),
+    };
+    let token = token::generate();
+    match insert_user(&body.user_name, &mail, &token::hash(&token), &data).await {
+        Ok(id) => {
+            send_verification(&mail, &token, &data).await;
+            HttpResponse::Ok().json(GetIdSchema { id })
+        }
+        Err(e) => mail_error(e),
+    }
+}
+
+fn mail_error(e: sqlx::Error) -> HttpResponse {
+    if e.to_string().contains("duplicate key value violates unique constraint") {
+        return HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "User with that mail already exists"}));
+    }
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+fn invalid_mail() -> HttpResponse {
+    HttpResponse::BadRequest().json(json!({"status": "fail","message": "Invalid mail address"}))
+}
+
+fn mail_not_found(mail: &str) -> HttpResponse {
+    let message = format!("Mail {} not found", mail);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+/// A failed delivery is only logged, the user can request a new link.
+async fn send_verification(mail: &str, token: &str, data: &web::Data<AppState>) {
+    let public_url = std::env::var("PUBLIC_URL").unwrap_or_else(|_| "http://localhost:8000".to_string());
+    let message = Mail {
+        to: mail.to_string(),
+        subject: "Verify your mail address".to_string(),
+        body: format!(
+            "Open this link to verify your mail address:\n\n{}/api/mails/verify?token={}\n\nThe link expires in {} hours.",
+            public_url.trim_end_matches('/'),
+            token,
+            VERIFICATION_TTL_HOURS
+        ),
+    };
+    if let Err(e) = data.mailer.send(&message).await {
+        println!("🔥 Verification mail to {} failed: {}", mail, e);
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, the primary mail first", body = Vec<UserMailModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/users/me/mails")]
+pub async fn get_my_mails(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_mails(user.id, &data).await {
+        Ok(mails) => HttpResponse::Ok().json(mails),
+        Err(e) => mail_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 201, description = "Created, a verification link is sent to the mail", body = UserMailModel),
+(status = 400, description = "Invalid mail or mail already in use", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = AddMail),
+)]
+#[post("/users/me/mails")]
+pub async fn add_mail(
+    user: AuthUser,
+    body: web::Json<AddMail>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let mail = match normalize_mail(&body.mail) {
+        Some(mail) => mail,
+        None => return invalid_mail(),
+    };
+    let token = token::generate();
+    match insert_mail(user.id, &mail, &token::hash(&token), &data).await {
+        Ok(created) => {
+            send_verification(&mail, &token, &data).await;
+            HttpResponse::Created().json(created)
+        }
+        Err(e) => mail_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Removed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 404, description = "Mail not found", body = String),
+(status = 409, description = "The primary mail cannot be removed", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(("mail" = String, Path, description = "Mail address")))]
+#[delete("/users/me/mails/{mail}")]
+pub async fn remove_mail(
+    user: AuthUser,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let mail = path.into_inner().trim().to_lowercase();
+    match delete_mail(user.id, &mail, &data).await {
+        Ok(MailUpdate::Updated) => HttpResponse::NoContent().finish(),
+        Ok(MailUpdate::Primary) => HttpResponse::Conflict().json(
+            json!({"status": "fail","message": "Make another mail primary before removing this one"}),
+        ),
+        Ok(_) => mail_not_found(&mail),
+        Err(e) => mail_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, all mails of the caller", body = Vec<UserMailModel>),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 404, description = "Mail not found", body = String),
+(status = 409, description = "Mail is not verified", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(("mail" = String, Path, description = "Mail address")))]
+#[put("/users/me/mails/{mail}/primary")]
+pub async fn make_mail_primary(
+    user: AuthUser,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let mail = path.into_inner().trim().to_lowercase();
+    let query_result = match set_primary_mail(user.id, &mail, &data).await {
+        Ok(MailUpdate::Updated) => select_mails(user.id, &data).await,
+        Ok(MailUpdate::Unverified) => {
+            return HttpResponse::Conflict()
+                .json(json!({"status": "fail","message": "Only verified mails can become primary"}));
+        }
+        Ok(_) => return mail_not_found(&mail),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(mails) => HttpResponse::Ok().json(mails),
+        Err(e) => mail_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 202, description = "A new verification link is sent, earlier links stop working"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 403, description = "Called with an API key", body = String),
+(status = 404, description = "No unverified mail with that address", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(("mail" = String, Path, description = "Mail address")))]
+#[post("/users/me/mails/{mail}/verification")]
+pub async fn resend_mail_verification(
+    user: AuthUser,
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if user.is_api_key() {
+        return session_required();
+    }
+    let mail = path.into_inner().trim().to_lowercase();
+    let token = token::generate();
+    match renew_verification_token(user.id, &mail, &token::hash(&token), &data).await {
+        Ok(true) => {
+            send_verification(&mail, &token, &data).await;
+            HttpResponse::Accepted().finish()
+        }
+        Ok(false) => mail_not_found(&mail),
+        Err(e) => mail_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "Mail verified", body = String),
+(status = 400, description = "Invalid, used or expired token", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(("token" = String, Query, description = "Token from the verification mail")))]
+#[get("/mails/verify")]
+pub async fn verify_mail_token(
+    opts: web::Query<VerifyMailOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match verify_mail(&token::hash(&opts.token), &data).await {
+        Ok(Verification::Verified(mail)) => {
+            HttpResponse::Ok().json(json!({"status": "success","mail": mail}))
+        }
+        Ok(Verification::Expired) => HttpResponse::BadRequest().json(
+            json!({"status": "fail","message": "Verification link expired, request a new one"}),
+        ),
+        Ok(Verification::Invalid) => HttpResponse::BadRequest()
+            .json(json!({"status": "fail","message": "Invalid verification link"})),
+        Err(e) => mail_error(e),
+    }
+}
+
+fn user_not_found(id: Uuid) -> HttpResponse {
+    let message = format!("User with ID: {} not found", id);
+    HttpResponse::NotFound().json(json!({"status": "fail","message": message}))
+}
+
+fn profile_error(e: impl std::fmt::Debug) -> HttpResponse {
+    HttpResponse::InternalServerError()
+        .json(json!({"status": "error","message": format!("{:?}", e)}))
+}
+
+/// Checks the limits of the profile columns, so violations answer with 400 instead of 500.
+fn invalid_profile(profile: &UpdateProfile) -> Option<String> {
+    let too_long = |value: &Option<String>, max: usize| {
+        value.as_deref().is_some_and(|value| value.trim().chars().count() > max)
+    };
+    if too_long(&profile.display_name, 100) {
+        return Some("displayName must have at most 100 characters".to_string());
+    }
+    if too_long(&profile.bio, 1000) {
+        return Some("bio must have at most 1000 characters".to_string());
+    }
+    if too_long(&profile.location, 100) {
+        return Some("location must have at most 100 characters".to_string());
+    }
+    match profile.website.as_deref().map(str::trim) {
+        Some(website) if website.len() > 255 => Some("website must have at most 255 characters".to_string()),
+        Some(website)
+            if !website.is_empty() && !website.starts_with("http://") && !website.starts_with("https://") =>
+        {
+            Some("website must be an http(s) URL".to_string())
+        }
+        _ => None,
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = UserProfileModel),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[get("/users/me")]
+pub async fn get_my_profile(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match select_profile(user.id, &data).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => user_not_found(user.id),
+        Err(e) => profile_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK", body = UserProfileModel),
+(status = 400, description = "Field too long, invalid website or unknown field", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = UpdateProfile, description="only supplied parameters are changed, empty strings clear them"),
+)]
+#[patch("/users/me")]
+pub async fn edit_my_profile(
+    user: AuthUser,
+    body: web::Json<UpdateProfile>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    if let Some(message) = invalid_profile(&body) {
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    let query_result = match update_profile(user.id, &body, &data).await {
+        Ok(true) => select_profile(user.id, &data).await,
+        Ok(false) => Ok(None),
+        Err(e) => Err(e),
+    };
+    match query_result {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        Ok(None) => user_not_found(user.id),
+        Err(e) => profile_error(e),
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "OK, with the user's public files", body = PublicProfileModel),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/users/{id}/profile")]
+pub async fn get_public_profile(
+    path: web::Path<Uuid>,
+    opts: web::Query<FilterOptions>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let limit = opts.limit.unwrap_or(10);
+    let offset = (opts.page.unwrap_or(1).max(1) - 1) * limit;
+
+    let profile = match select_profile(user_id, &data).await {
+        Ok(Some(profile)) => profile,
+        Ok(None) => return user_not_found(user_id),
+        Err(e) => return profile_error(e),
+    };
+    let stats = match select_profile_stats(user_id, &data).await {
+        Ok(stats) => stats,
+        Err(e) => return profile_error(e),
+    };
+    let public_files = match select_public_files_of_user(user_id, limit, offset, &data).await {
+        Ok(files) => files,
+        Err(e) => return profile_error(e),
+    };
+
+    HttpResponse::Ok().json(PublicProfileModel {
+        id: profile.id,
+        user_name: profile.user_name,
+        display_name: profile.display_name,
+        bio: profile.bio,
+        location: profile.location,
+        website: profile.website,
+        has_avatar: profile.has_avatar,
+        prints_made: stats.prints_made,
+        prints_of_files: stats.prints_of_files,
+        average_rating: stats.average_rating,
+        public_files,
+    })
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Avatar replaced"),
+(status = 400, description = "Unsupported content type or image too large", body = String),
+(status = 401, description = "Not authenticated", body = String),
+(status = 404, description = "User not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+request_body(content = Vec<u8>, description = "JPEG, PNG or WebP image", content_type = "image/jpeg"),
+)]
+#[put("/users/me/avatar")]
+pub async fn upload_avatar(
+    user: AuthUser,
+    req: HttpRequest,
+    body: web::Bytes,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !IMAGE_CONTENT_TYPES.contains(&content_type) {
+        let message = format!("Content-Type must be one of {}", IMAGE_CONTENT_TYPES.join(", "));
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+    if body.is_empty() || body.len() > MAX_IMAGE_BYTES {
+        let message = format!("Avatar must be between 1 and {} bytes", MAX_IMAGE_BYTES);
+        return HttpResponse::BadRequest().json(json!({"status": "fail","message": message}));
+    }
+
+    // a new blob per upload, so the old avatar stays readable until the account points away
+    let avatar = Avatar { id: Uuid::new_v4(), content_type: content_type.to_string() };
+    if let Err(e) = data.storage.write(avatar.id, &body).await {
+        return profile_error(e);
+    }
+    match replace_avatar(user.id, Some(&avatar), &data).await {
+        Ok(previous) => {
+            if let Some(previous) = previous {
+                let _ = data.storage.delete(previous).await;
+            }
+            HttpResponse::NoContent().finish()
+        }
         Err(e) => {
-            if e.to_string()
-                .contains("duplicate key value violates unique constraint")
-            {
-                return HttpResponse::BadRequest().json(
-                    json!({"status": "fail","message": "User with that mail already exists"}),
-                );
+            let _ = data.storage.delete(avatar.id).await;
+            profile_error(e)
+        }
+    }
+}
+
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 204, description = "Avatar removed"),
+(status = 401, description = "Not authenticated", body = String),
+(status = 500, description = "Internal server error", body = String)
+))]
+#[delete("/users/me/avatar")]
+pub async fn remove_avatar(
+    user: AuthUser,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    match replace_avatar(user.id, None, &data).await {
+        Ok(previous) => {
+            if let Some(previous) = previous {
+                let _ = data.storage.delete(previous).await;
             }
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => profile_error(e),
+    }
+}
 
-            HttpResponse::InternalServerError()
-                .json(json!({"status": "error","message": format!("{:?}", e)}))
+#[utoipa::path(
+context_path = "/api",
+responses(
+(status = 200, description = "Image content"),
+(status = 404, description = "User or avatar not found", body = String),
+(status = 500, description = "Internal server error", body = String)
+),
+params(
+("id" = String, Path, description = "User Uuid (e.g 2b377fba-903f-4957-b33d-3ed2c2b2b848)")
+))]
+#[get("/users/{id}/avatar")]
+pub async fn get_avatar(
+    path: web::Path<Uuid>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let user_id = path.into_inner();
+    let avatar = match select_avatar(user_id, &data).await {
+        Ok(Some(avatar)) => avatar,
+        Ok(None) => {
+            let message = format!("User with ID: {} has no avatar", user_id);
+            return HttpResponse::NotFound().json(json!({"status": "fail","message": message}));
         }
+        Err(e) => return profile_error(e),
     };
+    match data.storage.read(avatar.id).await {
+        Ok(content) => HttpResponse::Ok().content_type(avatar.content_type).body(content),
+        Err(e) => profile_error(e),
+    }
 }